@@ -0,0 +1,331 @@
+#[cfg(feature = "compression")]
+use flate2::bufread::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use std::fmt;
+#[cfg(feature = "compression")]
+use std::io::BufReader;
+use std::io::{self, BufRead, Error, ErrorKind, Read, Write};
+#[cfg(feature = "compression")]
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+/// The body of a [`Request`](struct.Request.html).
+#[derive(Clone, Debug)]
+pub(crate) enum Body {
+    /// A body held in memory in its entirety.
+    Bytes(Vec<u8>),
+    /// A body streamed from a reader as it is being sent.
+    Reader(BodyReader),
+}
+
+impl Body {
+    /// Writes the body to `stream`, using chunked encoding if it is
+    /// a reader of unknown length.
+    pub(crate) fn write_to<W: Write>(self, stream: &mut W) -> Result<(), Error> {
+        match self {
+            Body::Bytes(bytes) => stream.write_all(&bytes),
+            Body::Reader(reader) => {
+                let length = reader.length;
+                let mut reader = match reader.take() {
+                    Some(reader) => reader,
+                    None => {
+                        return Err(Error::other(
+                            "the request body reader has already been consumed",
+                        ))
+                    }
+                };
+                match length {
+                    Some(length) => {
+                        let written = io::copy(&mut reader.by_ref().take(length), stream)?;
+                        if written < length {
+                            return Err(Error::new(
+                                ErrorKind::UnexpectedEof,
+                                format!(
+                                    "the request body ended after {} of {} bytes",
+                                    written, length
+                                ),
+                            ));
+                        }
+                        Ok(())
+                    }
+                    None => write_chunked(&mut reader, stream),
+                }
+            }
+        }
+    }
+}
+
+/// A request body reader, shared between clones of the request it
+/// belongs to. Only one of them gets to send it.
+#[derive(Clone)]
+pub(crate) struct BodyReader {
+    reader: Arc<Mutex<Option<Box<dyn Read + Send>>>>,
+    pub(crate) length: Option<u64>,
+}
+
+impl BodyReader {
+    pub(crate) fn new<R: Read + Send + 'static>(reader: R, length: Option<u64>) -> BodyReader {
+        BodyReader {
+            reader: Arc::new(Mutex::new(Some(Box::new(reader)))),
+            length,
+        }
+    }
+
+    fn take(&self) -> Option<Box<dyn Read + Send>> {
+        match self.reader.lock() {
+            Ok(mut reader) => reader.take(),
+            Err(_) => None,
+        }
+    }
+}
+
+impl fmt::Debug for BodyReader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BodyReader {{ length: {:?} }}", self.length)
+    }
+}
+
+/// Copies `reader` to `stream` with `Transfer-Encoding: chunked`,
+/// one chunk per read, ending with the terminating zero-sized chunk.
+fn write_chunked<R: Read, W: Write>(reader: &mut R, stream: &mut W) -> Result<(), Error> {
+    let mut buf = [0; 8192];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(n) => n,
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        if n == 0 {
+            return stream.write_all(b"0\r\n\r\n");
+        }
+        write!(stream, "{:x}\r\n", n)?;
+        stream.write_all(&buf[..n])?;
+        stream.write_all(b"\r\n")?;
+    }
+}
+
+/// A stream a response body is read from, which wants to know when
+/// the end of the body has been reached, eg. to reuse the connection
+/// for another request.
+pub(crate) trait BodyEnd {
+    /// Called once the whole body has been read.
+    fn end_of_body(&mut self);
+}
+
+/// A reader that decodes a `Transfer-Encoding: chunked` body, yielding
+/// only the entity bytes.
+///
+/// Chunk extensions are ignored, and the trailer section following
+/// the terminating zero-sized chunk is consumed and discarded.
+pub(crate) struct ChunkedReader<R: BufRead> {
+    inner: R,
+    remaining: u64,
+    done: bool,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    pub(crate) fn new(inner: R) -> ChunkedReader<R> {
+        ChunkedReader {
+            inner,
+            remaining: 0,
+            done: false,
+        }
+    }
+
+    /// Reads the next chunk-size line, returning the size of the chunk.
+    fn read_chunk_size(&mut self) -> Result<u64, Error> {
+        let line = read_line(&mut self.inner)?;
+        // Anything after a ';' is a chunk extension, which we don't use.
+        let size = line.split(';').next().unwrap_or("").trim();
+        u64::from_str_radix(size, 16).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid chunk size: {:?}", size),
+            )
+        })
+    }
+
+    /// Consumes the trailer section, up to and including the final
+    /// empty line.
+    fn read_trailers(&mut self) -> Result<(), Error> {
+        while !read_line(&mut self.inner)?.is_empty() {}
+        Ok(())
+    }
+}
+
+impl<R: BufRead + BodyEnd> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            let size = self.read_chunk_size()?;
+            if size == 0 {
+                self.read_trailers()?;
+                self.done = true;
+                self.inner.end_of_body();
+                return Ok(0);
+            }
+            self.remaining = size;
+        }
+
+        let max = buf.len().min(self.remaining as usize);
+        let n = self.inner.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "connection closed in the middle of a chunk",
+            ));
+        }
+        self.remaining -= n as u64;
+        if self.remaining == 0 && !read_line(&mut self.inner)?.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "chunk data was not followed by CRLF",
+            ));
+        }
+        Ok(n)
+    }
+}
+
+/// A reader that yields exactly `Content-Length` bytes of a body.
+///
+/// Reaching the declared length is reported as EOF, whereas the
+/// connection closing before that is reported as an
+/// [`UnexpectedEof`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.UnexpectedEof)
+/// error, so a truncated body can't be mistaken for a complete one.
+pub(crate) struct LengthReader<R: Read> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> LengthReader<R> {
+    pub(crate) fn new(inner: R, length: u64) -> LengthReader<R> {
+        LengthReader {
+            inner,
+            remaining: length,
+        }
+    }
+}
+
+impl<R: Read + BodyEnd> Read for LengthReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let max = buf.len().min(self.remaining as usize);
+        let n = self.inner.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "connection closed with {} bytes of the body left unread",
+                    self.remaining
+                ),
+            ));
+        }
+        self.remaining -= n as u64;
+        if self.remaining == 0 {
+            self.inner.end_of_body();
+        }
+        Ok(n)
+    }
+}
+
+/// Wraps `body` in decoders undoing the given `Content-Encoding`.
+///
+/// Codings are listed in the order they were applied, so they're
+/// undone in reverse. If any of them isn't supported, the body is
+/// returned as-is, still encoded.
+#[cfg(feature = "compression")]
+pub(crate) fn decompress(body: Box<dyn BufRead>, encoding: Option<&str>) -> Box<dyn BufRead> {
+    let codings: Vec<String> = match encoding {
+        Some(encoding) => encoding
+            .split(',')
+            .map(|c| c.trim().to_ascii_lowercase())
+            .filter(|c| !c.is_empty() && c != "identity")
+            .collect(),
+        None => return body,
+    };
+    let supported = |c: &String| matches!(c.as_str(), "gzip" | "x-gzip" | "deflate");
+    if !codings.iter().all(supported) {
+        return body;
+    }
+
+    codings
+        .iter()
+        .rev()
+        .fold(body, |body, coding| -> Box<dyn BufRead> {
+            if coding == "deflate" {
+                Box::new(BufReader::new(Decoder::Deflate(body)))
+            } else {
+                Box::new(BufReader::new(Decoder::Gzip(GzDecoder::new(body))))
+            }
+        })
+}
+
+/// Decodes a `gzip` or `deflate` body. Deflate data should be
+/// zlib-wrapped, but some servers send raw deflate data instead, so
+/// which one it is is decided by looking for a zlib header on the
+/// first read.
+///
+/// Once the compressed data ends, the rest of the body is read too,
+/// eg. the terminating chunk of a chunked body, so that the end of
+/// the body is reached and the connection can be reused.
+#[cfg(feature = "compression")]
+enum Decoder {
+    Gzip(GzDecoder<Box<dyn BufRead>>),
+    Deflate(Box<dyn BufRead>),
+    Zlib(ZlibDecoder<Box<dyn BufRead>>),
+    Raw(DeflateDecoder<Box<dyn BufRead>>),
+}
+
+#[cfg(feature = "compression")]
+impl Read for Decoder {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if let Decoder::Deflate(ref mut body) = *self {
+            let zlib = is_zlib_header(body.fill_buf()?);
+            let body = mem::replace(body, Box::new(io::empty()));
+            *self = if zlib {
+                Decoder::Zlib(ZlibDecoder::new(body))
+            } else {
+                Decoder::Raw(DeflateDecoder::new(body))
+            };
+        }
+        let (n, body) = match *self {
+            Decoder::Gzip(ref mut decoder) => (decoder.read(buf)?, decoder.get_mut()),
+            Decoder::Zlib(ref mut decoder) => (decoder.read(buf)?, decoder.get_mut()),
+            Decoder::Raw(ref mut decoder) => (decoder.read(buf)?, decoder.get_mut()),
+            Decoder::Deflate(_) => unreachable!(),
+        };
+        if n == 0 && !buf.is_empty() {
+            io::copy(body, &mut io::sink())?;
+        }
+        Ok(n)
+    }
+}
+
+/// Checks whether `data` starts with a zlib header: deflate as the
+/// compression method, and a check value making the first two bytes a
+/// multiple of 31. Too little data to tell is taken to be zlib.
+#[cfg(feature = "compression")]
+fn is_zlib_header(data: &[u8]) -> bool {
+    match *data {
+        [cmf, flg, ..] => cmf & 0x0F == 8 && (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0,
+        _ => true,
+    }
+}
+
+/// Reads a CRLF-terminated line, returning it without the line ending.
+/// Hitting EOF before the end of the line is an error.
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, Error> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.ends_with('\n') {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "connection closed in the middle of a chunked body",
+        ));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
@@ -0,0 +1,269 @@
+use crate::cookies::CookieJar;
+use crate::digest::DigestAuth;
+use crate::http::{Method, Request};
+use crate::pool::Pool;
+use crate::proxy::Proxy;
+use crate::redirect::RedirectPolicy;
+#[cfg(feature = "https")]
+use crate::tls::TlsConfig;
+use crate::url::{ParseError, Url};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A client for sending [`Request`](struct.Request.html)s, which keeps
+/// connections alive between them.
+///
+/// Once the body of a response has been read completely, its
+/// connection is kept open and reused for the next request to the
+/// same host and port, saving the time spent connecting and, for
+/// HTTPS, on the TLS handshake. Servers can opt out of this by
+/// sending `Connection: close`, and limit the time an idle connection
+/// is kept with `Keep-Alive: timeout=n`.
+///
+/// A client also holds defaults for the requests created with it: a
+/// base URL, headers and a timeout. Each request starts out with
+/// these, and can override them with its own values.
+///
+/// Clones of a client share its connections.
+///
+/// # Example
+/// ```no_run
+/// # use std::io::Read;
+/// let client = mrq::Client::new()
+///     .with_base_url("http://example.com/api/")
+///     .with_header("Authorization", "Bearer hunter2")
+///     .with_timeout(10);
+/// for id in 0..10 {
+///     let mut response = client.get(format!("/item/{}", id)).send()?;
+///     // The connection is only reused once the body has been read.
+///     let mut body = String::new();
+///     response.body.read_to_string(&mut body)?;
+/// }
+/// # Ok::<(), mrq::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Client {
+    pool: Arc<Pool>,
+    base_url: Option<String>,
+    headers: HashMap<String, String>,
+    timeout: Option<u64>,
+    cookie_jar: Option<Arc<CookieJar>>,
+    proxy: Option<Proxy>,
+    digest_auth: Option<Arc<DigestAuth>>,
+    redirect_policy: RedirectPolicy,
+    #[cfg(feature = "https")]
+    tls_config: Option<Arc<TlsConfig>>,
+}
+
+impl Client {
+    /// Creates a new `Client`, which keeps up to 8 idle connections
+    /// per host for up to 90 seconds.
+    pub fn new() -> Client {
+        Client {
+            pool: Arc::new(Pool::new(8, Duration::from_secs(90))),
+            base_url: None,
+            headers: HashMap::new(),
+            timeout: None,
+            cookie_jar: None,
+            proxy: None,
+            digest_auth: None,
+            redirect_policy: RedirectPolicy::default(),
+            #[cfg(feature = "https")]
+            tls_config: None,
+        }
+    }
+
+    /// Sets the URL that relative URLs passed to this client are
+    /// appended to, eg. with a base URL of `http://example.com/api`,
+    /// `client.get("/users")` requests `http://example.com/api/users`.
+    /// URLs with a scheme are used as-is, and fail with
+    /// [`Error::InvalidUrl`](enum.Error.html) if they aren't valid.
+    pub fn with_base_url<T: Into<String>>(mut self, base_url: T) -> Client {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Adds a header to every request created with this client.
+    pub fn with_header<T: Into<String>, U: Into<String>>(mut self, key: T, value: U) -> Client {
+        let key = key.into();
+        self.headers.retain(|k, _| !k.eq_ignore_ascii_case(&key));
+        self.headers.insert(key, value.into());
+        self
+    }
+
+    /// Adds headers to every request created with this client.
+    pub fn with_headers(self, headers: &HashMap<String, String>) -> Client {
+        headers.iter().fold(self, |client, (k, v)| {
+            client.with_header(k.as_str(), v.as_str())
+        })
+    }
+
+    /// Sets the timeout of every request created with this client.
+    pub fn with_timeout(mut self, timeout: u64) -> Client {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum number of idle connections kept per host. Zero
+    /// disables keeping connections alive.
+    pub fn with_max_idle_connections(mut self, max: usize) -> Client {
+        self.pool = Arc::new(Pool::new(max, self.pool.idle_timeout));
+        self
+    }
+
+    /// Sets the number of seconds an idle connection is kept before
+    /// it is closed.
+    pub fn with_idle_timeout(mut self, timeout: u64) -> Client {
+        self.pool = Arc::new(Pool::new(
+            self.pool.max_idle_per_host,
+            Duration::from_secs(timeout),
+        ));
+        self
+    }
+
+    /// Stores the cookies set by the responses to this client's
+    /// requests in `jar`, and sends them along with later requests,
+    /// as described in [`CookieJar`](struct.CookieJar.html).
+    pub fn with_cookie_jar(mut self, jar: CookieJar) -> Client {
+        self.cookie_jar = Some(Arc::new(jar));
+        self
+    }
+
+    /// Returns the cookie jar of this client, if it has one.
+    pub fn cookie_jar(&self) -> Option<&CookieJar> {
+        self.cookie_jar.as_deref()
+    }
+
+    /// Sends every request created with this client through `proxy`,
+    /// see [`Request::with_proxy`](struct.Request.html#method.with_proxy).
+    pub fn with_proxy(mut self, proxy: Proxy) -> Client {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Sets the credentials for HTTP Digest authentication, see
+    /// [`Request::with_digest_auth`](struct.Request.html#method.with_digest_auth).
+    /// The requests created with this client share the last challenge
+    /// received from each server, and answer it without waiting for
+    /// another `401` when they're sent to that same server.
+    pub fn with_digest_auth<T: Into<String>, U: Into<String>>(
+        mut self,
+        username: T,
+        password: U,
+    ) -> Client {
+        self.digest_auth = Some(Arc::new(DigestAuth::new(username.into(), password.into())));
+        self
+    }
+
+    /// Sets which redirects are followed for every request created
+    /// with this client, see [`RedirectPolicy`](enum.RedirectPolicy.html).
+    pub fn with_redirect_policy(mut self, policy: RedirectPolicy) -> Client {
+        self.redirect_policy = policy;
+        self
+    }
+
+    /// Sets the TLS settings of every request created with this
+    /// client, see [`TlsConfig`](struct.TlsConfig.html).
+    #[cfg(feature = "https")]
+    pub fn with_tls_config(mut self, tls_config: TlsConfig) -> Client {
+        self.tls_config = Some(Arc::new(tls_config));
+        self
+    }
+
+    /// Creates a request to `url` with `method`, to be sent using this
+    /// client's connections and defaults.
+    pub fn request<T: Into<String>>(&self, method: Method, url: T) -> Request {
+        let mut request =
+            Request::new(method, self.resolve(url.into())).with_headers(&self.headers);
+        request.timeout = self.timeout;
+        request.pool = Some(self.pool.clone());
+        request.cookie_jar = self.cookie_jar.clone();
+        request.proxy = self.proxy.clone();
+        request.digest_auth = self.digest_auth.clone();
+        request.redirect_policy = self.redirect_policy.clone();
+        #[cfg(feature = "https")]
+        {
+            request.tls_config = self.tls_config.clone();
+        }
+        request
+    }
+
+    /// Appends `url` to the base URL, unless it has a scheme.
+    fn resolve(&self, url: String) -> String {
+        match self.base_url {
+            Some(ref base_url) if matches!(Url::parse(&url), Err(ParseError::MissingScheme)) => {
+                if url.is_empty() || url.starts_with('?') {
+                    format!("{}{}", base_url, url)
+                } else {
+                    format!(
+                        "{}/{}",
+                        base_url.trim_end_matches('/'),
+                        url.trim_start_matches('/')
+                    )
+                }
+            }
+            _ => url,
+        }
+    }
+
+    /// Alias for [request](#method.request) with `method` set to
+    /// [Method::Get](enum.Method.html).
+    pub fn get<T: Into<String>>(&self, url: T) -> Request {
+        self.request(Method::Get, url)
+    }
+
+    /// Alias for [request](#method.request) with `method` set to
+    /// [Method::Head](enum.Method.html).
+    pub fn head<T: Into<String>>(&self, url: T) -> Request {
+        self.request(Method::Head, url)
+    }
+
+    /// Alias for [request](#method.request) with `method` set to
+    /// [Method::Post](enum.Method.html).
+    pub fn post<T: Into<String>>(&self, url: T) -> Request {
+        self.request(Method::Post, url)
+    }
+
+    /// Alias for [request](#method.request) with `method` set to
+    /// [Method::Put](enum.Method.html).
+    pub fn put<T: Into<String>>(&self, url: T) -> Request {
+        self.request(Method::Put, url)
+    }
+
+    /// Alias for [request](#method.request) with `method` set to
+    /// [Method::Delete](enum.Method.html).
+    pub fn delete<T: Into<String>>(&self, url: T) -> Request {
+        self.request(Method::Delete, url)
+    }
+
+    /// Alias for [request](#method.request) with `method` set to
+    /// [Method::Connect](enum.Method.html).
+    pub fn connect<T: Into<String>>(&self, url: T) -> Request {
+        self.request(Method::Connect, url)
+    }
+
+    /// Alias for [request](#method.request) with `method` set to
+    /// [Method::Options](enum.Method.html).
+    pub fn options<T: Into<String>>(&self, url: T) -> Request {
+        self.request(Method::Options, url)
+    }
+
+    /// Alias for [request](#method.request) with `method` set to
+    /// [Method::Trace](enum.Method.html).
+    pub fn trace<T: Into<String>>(&self, url: T) -> Request {
+        self.request(Method::Trace, url)
+    }
+
+    /// Alias for [request](#method.request) with `method` set to
+    /// [Method::Patch](enum.Method.html).
+    pub fn patch<T: Into<String>>(&self, url: T) -> Request {
+        self.request(Method::Patch, url)
+    }
+}
+
+impl Default for Client {
+    fn default() -> Client {
+        Client::new()
+    }
+}
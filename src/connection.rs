@@ -1,114 +1,365 @@
-use crate::http::{parse_url, Request, Response, Status};
+use crate::body::Body;
+use crate::error::Error;
+use crate::http::{find_header, Method, Request, Response};
+use crate::pool::{PoolKey, PooledStream};
 #[cfg(feature = "https")]
-use rustls::{self, ClientConfig, ClientSession};
+use crate::proxy::connect_tunnel;
+use crate::proxy::{socks5_connect, Proxy};
+use crate::redirect::{is_redirect, Redirect};
+#[cfg(feature = "https")]
+use crate::tls::{TlsConfig, TlsInfo};
+use crate::url::Url;
+#[cfg(feature = "https")]
+use rustls::{self, Certificate, ClientSession, Session, StreamOwned};
 use std::env;
-use std::io::{BufReader, BufWriter, Error, Write};
+use std::io::{self, BufRead, BufWriter, Read, Write};
 use std::net::TcpStream;
-#[cfg(feature = "https")]
-use std::sync::Arc;
 use std::time::Duration;
-#[cfg(feature = "https")]
-use webpki::DNSNameRef;
-#[cfg(feature = "https")]
-use webpki_roots::TLS_SERVER_ROOTS;
 
 /// A connection to the server for sending
 /// [`Request`](struct.Request.html)s.
 pub struct Connection {
     request: Request,
+    pub(crate) url: Url,
     timeout: Option<u64>,
+    proxy: Option<Proxy>,
 }
 
 impl Connection {
     /// Creates a new `Connection`. See
     /// [`Request`](struct.Request.html) for specifics about *what* is
-    /// being sent.
-    pub(crate) fn new(request: Request) -> Connection {
+    /// being sent. Fails if the request's URL is invalid, or if the
+    /// proxy set by the environment is.
+    pub(crate) fn new(request: Request) -> Result<Connection, Error> {
+        let url = request.url.clone()?;
+        let proxy = match request.proxy {
+            Some(ref proxy) => Some(proxy.clone()),
+            None => Proxy::from_env(&url)?,
+        };
         let timeout = request
             .timeout
             .or_else(|| match env::var("MINREQ_TIMEOUT") {
                 Ok(t) => t.parse::<u64>().ok(),
                 Err(_) => None,
             });
-        Connection { request, timeout }
+        Ok(Connection {
+            request,
+            url,
+            timeout,
+            proxy,
+        })
     }
 
     /// Sends the [`Request`](struct.Request.html), consumes this
     /// connection, and returns a [`Response`](struct.Response.html).
-    #[cfg(feature = "https")]
-    pub(crate) fn send_https(self) -> Result<Response, Error> {
+    ///
+    /// If the request has a pool, an idle connection from it is used
+    /// if there is one the server hasn't closed. Should the server
+    /// close it while the request is being sent, the request is
+    /// retried once on a new connection, unless its body was streamed
+    /// and can't be resent. Once the request has been sent, it is only
+    /// retried if it is idempotent and the server closed the
+    /// connection without answering, since the server may have acted
+    /// on it already.
+    pub(crate) fn send(self) -> Result<Response, Error> {
         let req_copy = self.request.clone();
-        let host = self.request.host.clone();
-        let bytes = self.request.into_string().into_bytes();
+        let pool = match self.request.pool {
+            Some(ref pool) => {
+                let key = PoolKey::new(&self.url, self.proxy.as_ref());
+                #[cfg(feature = "https")]
+                let key = key.with_tls_config(self.request.tls_config.as_ref());
+                Some((pool.clone(), key))
+            }
+            None => None,
+        };
 
+        let idle = match pool {
+            Some((ref pool, ref key)) => pool.take(key),
+            None => None,
+        };
+        let reused = match idle {
+            Some(stream) => {
+                stream.set_timeout(self.timeout)?;
+                let stream = PooledStream::new(stream, pool.clone());
+                let resendable = !matches!(req_copy.body, Some(Body::Reader(_)));
+                let request = req_copy.clone();
+                match write_request(&self.url, self.proxy.as_ref(), request, stream) {
+                    Ok(mut stream) => {
+                        let retry = resendable && is_idempotent(&req_copy.method);
+                        match stream.fill_buf().map(|buf| buf.is_empty()) {
+                            Ok(false) => Some(stream),
+                            Ok(true) if retry => None,
+                            Ok(true) => return Err(connection_closed().into()),
+                            Err(ref err) if retry && is_connection_closed(err) => None,
+                            Err(err) => return Err(err.into()),
+                        }
+                    }
+                    Err(_) if resendable => None,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            None => None,
+        };
+        let stream = match reused {
+            Some(stream) => stream,
+            None => {
+                let stream = PooledStream::new(self.connect()?, pool);
+                write_request(&self.url, self.proxy.as_ref(), self.request, stream)?
+            }
+        };
+
+        let resp = Response::from_stream(stream, &self.url, &req_copy)?;
+        if let Some(ref jar) = req_copy.cookie_jar {
+            for set_cookie in resp.header_values("Set-Cookie") {
+                jar.insert(&self.url, set_cookie);
+            }
+        }
+        Self::handle_redirect(req_copy, &self.url, resp)
+    }
+
+    /// Opens a new connection to the host of the request, or to the
+    /// proxy. SOCKS5 proxies are asked to connect to the host, and
+    /// HTTP proxies to open a tunnel to it for HTTPS.
+    fn connect(&self) -> Result<Stream, Error> {
+        let tcp = create_tcp_stream(&self.url, self.proxy.as_ref(), self.timeout)?;
+        #[cfg(feature = "https")]
+        {
+            if self.url.is_https() {
+                let mut tcp = tcp;
+                if let Some(proxy) = self.proxy.as_ref().filter(|p| p.is_http()) {
+                    connect_tunnel(&mut tcp, &self.url, proxy)?;
+                }
+                return self.connect_tls(tcp);
+            }
+        }
+        Ok(Stream::Tcp(tcp))
+    }
+
+    /// Sets up a TLS session on top of `tcp`.
+    #[cfg(feature = "https")]
+    fn connect_tls(&self, tcp: TcpStream) -> Result<Stream, Error> {
         // Rustls setup
-        let dns_name = host.clone();
-        let dns_name = dns_name.split(':').next().unwrap();
-        let dns_name = DNSNameRef::try_from_ascii_str(dns_name).unwrap();
-        let mut config = ClientConfig::new();
-        config
-            .root_store
-            .add_server_trust_anchors(&TLS_SERVER_ROOTS);
-        let sess = ClientSession::new(&Arc::new(config), dns_name);
-
-        // IO
-        let stream = create_tcp_stream(host, self.timeout)?;
-        let mut tls = rustls::StreamOwned::new(sess, stream);
-        let _ = tls.write(&bytes)?;
-        let resp = Response::from_stream(tls)?;
-        match resp.status {
-            Status::Redirect(_) => Self::handle_redirect(req_copy, resp),
-            _ => Ok(resp),
+        let tls_config = match self.request.tls_config {
+            Some(ref tls_config) => tls_config,
+            None => TlsConfig::shared_default(),
+        };
+        let mut sess = tls_config.client_session(self.url.host())?;
+        // The handshake is done right away, to know the certificates.
+        {
+            let mut tcp = &tcp;
+            while sess.is_handshaking() {
+                sess.complete_io(&mut tcp)?;
+            }
+        }
+        let certificates = tls_config.peer_certificates(&sess, self.url.host());
+
+        // Pinned keys are checked as soon as the handshake is done, so
+        // that nothing is sent to a server that doesn't have one.
+        if tls_config.has_pins() {
+            tls_config.check_pins(&certificates, self.url.host())?;
         }
+
+        Ok(Stream::Tls(
+            Box::new(StreamOwned::new(sess, tcp)),
+            certificates,
+        ))
     }
 
-    /// handle https redirect
-    fn handle_redirect(mut req: Request, resp: Response) -> Result<Response, Error> {
-        if let Some(loc) = resp.headers.get("Location") {
-            let url = if loc.starts_with("https://") || loc.starts_with("http://") {
-                loc.to_string()
-            } else {
-                let scheme = if req.https { "https://" } else { "http://" };
-                format!("{}{}{}", scheme, req.host, loc)
-            };
-            let (host, resource, https) = parse_url(url);
-            req.host = host;
-            req.resource = resource;
-            req.https = https;
+    /// Follows the redirect `resp` to `url` leads to, if it is one
+    /// and the request's redirect policy allows it. Otherwise `resp`
+    /// is returned as-is.
+    ///
+    /// `303` responses, and `301` and `302` responses to POST
+    /// requests, are followed with a GET request without the body.
+    /// Other redirects repeat the request, unless its body was
+    /// streamed and can't be sent again. Credentials are only sent
+    /// along if the redirect stays on the same origin.
+    fn handle_redirect(mut req: Request, url: &Url, resp: Response) -> Result<Response, Error> {
+        let status = i32::from(&resp.status);
+        let raw_location = match find_header(&resp.headers, "Location") {
+            Some(location) if is_redirect(status) => location.to_string(),
+            _ => return Ok(resp),
+        };
+        let location = url.join(&raw_location)?;
+
+        let to_get = match req.method {
+            Method::Head => false,
+            Method::Post => status != 307 && status != 308,
+            _ => status == 303,
+        };
+        if !to_get && matches!(req.body, Some(Body::Reader(_))) {
+            return Ok(resp);
+        }
+        let mut previous: Vec<Url> = req.history.iter().map(|hop| hop.url().clone()).collect();
+        previous.push(url.clone());
+        if !req.redirect_policy.follow(&location, &previous)? {
+            return Ok(resp);
+        }
+        // Without a cookie jar, nothing can change between two visits
+        // of the same URL with the same method, so the redirects would
+        // go on forever.
+        let same_method = !to_get || matches!(req.method, Method::Get);
+        if same_method && req.cookie_jar.is_none() && previous.contains(&location) {
+            return Err(Error::RedirectLoop(
+                location.without_credentials().to_string(),
+            ));
+        }
+
+        if to_get {
+            req.method = Method::Get;
             req.body = None;
-            req.send()
-        } else {
-            Ok(resp)
+            req.remove_header("Content-Length");
+            req.remove_header("Transfer-Encoding");
+            req.remove_header("Content-Type");
         }
+        if !location.same_origin(url) {
+            req.remove_header("Authorization");
+            req.remove_header("Cookie");
+            req.digest_auth = None;
+        }
+        req.history
+            .push(Redirect::new(url.clone(), resp.status, raw_location));
+        req.url = Ok(location);
+        req.send()
     }
+}
 
-    /// Sends the [`Request`](struct.Request.html), consumes this
-    /// connection, and returns a [`Response`](struct.Response.html).
-    pub(crate) fn send(self) -> Result<Response, Error> {
-        let req_copy = self.request.clone();
-        let host = self.request.host.clone();
-        let bytes = self.request.into_string().into_bytes();
-
-        let tcp = create_tcp_stream(host, self.timeout)?;
-
-        // Send request
-        let mut stream = BufWriter::new(tcp);
-        stream.write_all(&bytes)?;
-        let buf = BufReader::new(stream.into_inner()?);
-        let resp = Response::from_stream(buf)?;
-        match resp.status {
-            Status::Redirect(_) => Self::handle_redirect(req_copy, resp),
-            _ => Ok(resp),
+/// An open connection to a server.
+pub(crate) enum Stream {
+    /// A plain HTTP connection.
+    Tcp(TcpStream),
+    /// An HTTPS connection, and the certificates the server
+    /// presented.
+    #[cfg(feature = "https")]
+    Tls(Box<StreamOwned<ClientSession, TcpStream>>, Vec<Certificate>),
+}
+
+impl Stream {
+    /// Returns the details of the TLS session, for HTTPS connections.
+    #[cfg(feature = "https")]
+    pub(crate) fn tls_info(&self) -> Option<TlsInfo> {
+        match *self {
+            Stream::Tcp(_) => None,
+            Stream::Tls(ref tls, ref certificates) => Some(TlsInfo::new(&tls.sess, certificates)),
         }
     }
+
+    fn tcp(&self) -> &TcpStream {
+        match *self {
+            Stream::Tcp(ref tcp) => tcp,
+            #[cfg(feature = "https")]
+            Stream::Tls(ref tls, _) => &tls.sock,
+        }
+    }
+
+    /// Checks whether the server has closed the idle connection, or
+    /// sent something on it, either of which makes it unusable.
+    pub(crate) fn is_closed(&self) -> bool {
+        let tcp = self.tcp();
+        if tcp.set_nonblocking(true).is_err() {
+            return true;
+        }
+        let closed = match tcp.peek(&mut [0]) {
+            Err(ref err) => err.kind() != io::ErrorKind::WouldBlock,
+            Ok(_) => true,
+        };
+        tcp.set_nonblocking(false).is_err() || closed
+    }
+
+    /// Sets the read and write timeouts of the connection.
+    fn set_timeout(&self, timeout: Option<u64>) -> Result<(), io::Error> {
+        let dur = timeout.map(Duration::from_secs);
+        self.tcp().set_read_timeout(dur)?;
+        self.tcp().set_write_timeout(dur)
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            Stream::Tcp(ref mut tcp) => tcp.read(buf),
+            #[cfg(feature = "https")]
+            Stream::Tls(ref mut tls, _) => tls.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Stream::Tcp(ref mut tcp) => tcp.write(buf),
+            #[cfg(feature = "https")]
+            Stream::Tls(ref mut tls, _) => tls.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Stream::Tcp(ref mut tcp) => tcp.flush(),
+            #[cfg(feature = "https")]
+            Stream::Tls(ref mut tls, _) => tls.flush(),
+        }
+    }
+}
+
+/// Writes `request` to `stream`.
+fn write_request(
+    url: &Url,
+    proxy: Option<&Proxy>,
+    request: Request,
+    mut stream: PooledStream,
+) -> Result<PooledStream, io::Error> {
+    {
+        let mut writer = BufWriter::new(&mut stream);
+        request.write_to(url, proxy, &mut writer)?;
+    }
+    Ok(stream)
 }
 
-fn create_tcp_stream(host: String, timeout: Option<u64>) -> Result<TcpStream, Error> {
-    let stream = TcpStream::connect(host)?;
+/// Checks whether sending a request with `method` twice has the same
+/// effect as sending it once.
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        method,
+        Method::Get | Method::Head | Method::Put | Method::Delete | Method::Options | Method::Trace
+    )
+}
+
+/// Checks whether `err` means the server closed the connection.
+fn is_connection_closed(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+    )
+}
+
+fn connection_closed() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "the connection was closed by the server",
+    )
+}
+
+/// Connects to the host of `url`, either directly or through a
+/// SOCKS5 `proxy`. With an HTTP proxy, this connects to the proxy.
+fn create_tcp_stream(
+    url: &Url,
+    proxy: Option<&Proxy>,
+    timeout: Option<u64>,
+) -> Result<TcpStream, Error> {
+    let mut stream = match proxy {
+        Some(proxy) => TcpStream::connect((proxy.host(), proxy.port()))?,
+        None => TcpStream::connect((url.host(), url.port()))?,
+    };
     if let Some(secs) = timeout {
         let dur = Some(Duration::from_secs(secs));
         stream.set_read_timeout(dur)?;
         stream.set_write_timeout(dur)?;
     }
+    if let Some(proxy) = proxy.filter(|p| !p.is_http()) {
+        socks5_connect(&mut stream, url, proxy)?;
+    }
     Ok(stream)
 }
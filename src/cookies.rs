@@ -0,0 +1,466 @@
+use crate::url::Url;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A store of cookies, following the rules of
+/// [RFC 6265](https://tools.ietf.org/html/rfc6265).
+///
+/// When attached to a [`Client`](struct.Client.html) with
+/// [`with_cookie_jar`](struct.Client.html#method.with_cookie_jar),
+/// the jar captures the cookies set by every response, including the
+/// ones along redirects, and sends the matching ones in a `Cookie`
+/// header with each request.
+///
+/// # Example
+/// ```
+/// let jar = mrq::CookieJar::new();
+/// let url = mrq::Url::parse("http://example.com/account/login").unwrap();
+/// jar.insert(&url, "session=abc123; Path=/account; HttpOnly");
+///
+/// let url = mrq::Url::parse("http://example.com/account/settings").unwrap();
+/// assert_eq!(jar.cookie_header(&url), Some("session=abc123".to_string()));
+/// let url = mrq::Url::parse("http://example.com/").unwrap();
+/// assert_eq!(jar.cookie_header(&url), None);
+/// ```
+pub struct CookieJar {
+    cookies: Mutex<Vec<Cookie>>,
+    next_id: AtomicU64,
+}
+
+impl CookieJar {
+    /// Creates an empty cookie jar.
+    pub fn new() -> CookieJar {
+        CookieJar {
+            cookies: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Stores the cookie from a `Set-Cookie` header value received in
+    /// a response from `url`. Cookies the server at `url` isn't
+    /// allowed to set are ignored, and a cookie that has already
+    /// expired removes any stored cookie it would have replaced.
+    pub fn insert(&self, url: &Url, set_cookie: &str) {
+        let mut cookie = match Cookie::parse(url, set_cookie) {
+            Some(cookie) => cookie,
+            None => return,
+        };
+        let mut cookies = match self.cookies.lock() {
+            Ok(cookies) => cookies,
+            Err(_) => return,
+        };
+        let now = SystemTime::now();
+        cookies.retain(|c| !c.is_expired(now));
+        let old = cookies.iter().position(|c| {
+            c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path
+        });
+        match old {
+            Some(idx) => cookie.id = cookies.remove(idx).id,
+            None => cookie.id = self.next_id.fetch_add(1, Ordering::Relaxed),
+        }
+        if !cookie.is_expired(now) {
+            cookies.push(cookie);
+        }
+    }
+
+    /// Returns the value of the `Cookie` header for a request to
+    /// `url`, or `None` if no stored cookies match it.
+    pub fn cookie_header(&self, url: &Url) -> Option<String> {
+        let mut cookies = self.matching(url);
+        if cookies.is_empty() {
+            return None;
+        }
+        // Cookies with longer paths are listed first, and otherwise
+        // the ones that were set first.
+        cookies.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.id.cmp(&b.id)));
+        let pairs: Vec<String> = cookies
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+
+    /// Returns all of the cookies that haven't expired yet.
+    pub fn cookies(&self) -> Vec<Cookie> {
+        let now = SystemTime::now();
+        match self.cookies.lock() {
+            Ok(cookies) => cookies
+                .iter()
+                .filter(|c| !c.is_expired(now))
+                .cloned()
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Removes all cookies from the jar.
+    pub fn clear(&self) {
+        if let Ok(mut cookies) = self.cookies.lock() {
+            cookies.clear();
+        }
+    }
+
+    fn matching(&self, url: &Url) -> Vec<Cookie> {
+        let now = SystemTime::now();
+        let host = url.host().to_ascii_lowercase();
+        match self.cookies.lock() {
+            Ok(cookies) => cookies
+                .iter()
+                .filter(|c| !c.is_expired(now))
+                .filter(|c| {
+                    if c.host_only {
+                        host == c.domain
+                    } else {
+                        domain_match(&host, &c.domain)
+                    }
+                })
+                .filter(|c| path_match(url.path(), &c.path))
+                .filter(|c| !c.secure || url.is_https())
+                .cloned()
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+impl Default for CookieJar {
+    fn default() -> CookieJar {
+        CookieJar::new()
+    }
+}
+
+impl fmt::Debug for CookieJar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CookieJar {{ cookies: {:?} }}", self.cookies())
+    }
+}
+
+/// The `SameSite` attribute of a cookie.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    /// `SameSite=Strict`
+    Strict,
+    /// `SameSite=Lax`
+    Lax,
+    /// `SameSite=None`
+    None,
+}
+
+/// A cookie stored in a [`CookieJar`](struct.CookieJar.html).
+#[derive(Clone, Debug)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    domain: String,
+    host_only: bool,
+    path: String,
+    expires: Option<SystemTime>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+    id: u64,
+}
+
+impl Cookie {
+    /// Returns the name of the cookie.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value of the cookie.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Returns the domain the cookie is sent to. Unless the cookie is
+    /// [`host_only`](#method.host_only), it is also sent to the
+    /// domain's subdomains.
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    /// Returns true if the cookie had no `Domain` attribute, and is
+    /// only sent to the exact host that set it.
+    pub fn host_only(&self) -> bool {
+        self.host_only
+    }
+
+    /// Returns the path the cookie is sent to, along with the paths
+    /// below it.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns when the cookie expires, or `None` for a session
+    /// cookie, which lasts as long as the jar.
+    pub fn expires(&self) -> Option<SystemTime> {
+        self.expires
+    }
+
+    /// Returns true if the cookie is only sent over HTTPS.
+    pub fn secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Returns true if the cookie had the `HttpOnly` attribute.
+    pub fn http_only(&self) -> bool {
+        self.http_only
+    }
+
+    /// Returns the `SameSite` attribute of the cookie, if it had a
+    /// valid one.
+    pub fn same_site(&self) -> Option<SameSite> {
+        self.same_site
+    }
+
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Parses a `Set-Cookie` header value received from `url`, as
+    /// described in
+    /// [RFC 6265 section 5.2](https://tools.ietf.org/html/rfc6265#section-5.2),
+    /// returning `None` if the cookie should be ignored.
+    fn parse(url: &Url, set_cookie: &str) -> Option<Cookie> {
+        let mut attributes = set_cookie.split(';');
+        let pair = attributes.next()?;
+        let idx = pair.find('=')?;
+        let name = pair[..idx].trim();
+        if name.is_empty() {
+            return None;
+        }
+        let host = url.host().to_ascii_lowercase();
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: pair[idx + 1..].trim().to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url.path()),
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+            id: 0,
+        };
+
+        let mut max_age = None;
+        let mut expires = None;
+        let mut domain = None;
+        for attribute in attributes {
+            let (key, value) = match attribute.find('=') {
+                Some(idx) => (attribute[..idx].trim(), attribute[idx + 1..].trim()),
+                None => (attribute.trim(), ""),
+            };
+            if key.eq_ignore_ascii_case("Expires") {
+                if let Some(time) = parse_cookie_date(value) {
+                    expires = Some(time);
+                }
+            } else if key.eq_ignore_ascii_case("Max-Age") {
+                if let Some(time) = parse_max_age(value) {
+                    max_age = Some(time);
+                }
+            } else if key.eq_ignore_ascii_case("Domain") {
+                let value = value.trim_start_matches('.');
+                if !value.is_empty() {
+                    domain = Some(value.to_ascii_lowercase());
+                }
+            } else if key.eq_ignore_ascii_case("Path") {
+                if value.starts_with('/') {
+                    cookie.path = value.to_string();
+                }
+            } else if key.eq_ignore_ascii_case("Secure") {
+                cookie.secure = true;
+            } else if key.eq_ignore_ascii_case("HttpOnly") {
+                cookie.http_only = true;
+            } else if key.eq_ignore_ascii_case("SameSite") {
+                cookie.same_site = if value.eq_ignore_ascii_case("Strict") {
+                    Some(SameSite::Strict)
+                } else if value.eq_ignore_ascii_case("Lax") {
+                    Some(SameSite::Lax)
+                } else if value.eq_ignore_ascii_case("None") {
+                    Some(SameSite::None)
+                } else {
+                    None
+                };
+            }
+        }
+        cookie.expires = max_age.or(expires);
+
+        if let Some(domain) = domain {
+            // A cookie can only be set for the host itself or one of
+            // its parent domains, and never for a top-level domain as
+            // a whole.
+            if !domain_match(&host, &domain) || (domain != host && !domain.contains('.')) {
+                return None;
+            }
+            cookie.domain = domain;
+            cookie.host_only = false;
+        }
+        // Only secure origins may set secure cookies.
+        if cookie.secure && !url.is_https() {
+            return None;
+        }
+        Some(cookie)
+    }
+}
+
+/// Checks whether `host` is `domain` or one of its subdomains. IP
+/// addresses only match themselves.
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.len() > domain.len()
+            && host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.parse::<IpAddr>().is_err())
+}
+
+/// Checks whether `path` is `cookie_path` or below it.
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Returns the path a cookie is sent to when it has no `Path`
+/// attribute: the directory of the path that set it.
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(idx) => path[..idx].to_string(),
+    }
+}
+
+/// Parses a `Max-Age` value into the time it expires at. Values of
+/// zero or less expire the cookie right away.
+fn parse_max_age(value: &str) -> Option<SystemTime> {
+    if !value.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        return None;
+    }
+    let seconds = value.parse::<i64>().ok()?;
+    if seconds <= 0 {
+        return Some(UNIX_EPOCH);
+    }
+    let now = SystemTime::now();
+    // Ages too far in the future to be represented make the cookie a
+    // session cookie instead.
+    now.checked_add(Duration::from_secs(seconds as u64))
+}
+
+/// Parses the date of an `Expires` attribute, using the lenient
+/// algorithm of
+/// [RFC 6265 section 5.1.1](https://tools.ietf.org/html/rfc6265#section-5.1.1),
+/// which accepts all of the date formats servers use in practice.
+fn parse_cookie_date(date: &str) -> Option<SystemTime> {
+    let is_delimiter = |c: char| {
+        c == '\t'
+            || (' '..='/').contains(&c)
+            || (';'..='@').contains(&c)
+            || ('['..='`').contains(&c)
+            || ('{'..='~').contains(&c)
+    };
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+    for token in date.split(is_delimiter).filter(|t| !t.is_empty()) {
+        if time.is_none() {
+            if let Some(t) = parse_time(token) {
+                time = Some(t);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(d) = leading_digits(token, 1, 2) {
+                day = Some(d);
+                continue;
+            }
+        }
+        if month.is_none() {
+            if let Some(m) = parse_month(token) {
+                month = Some(m);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(y) = leading_digits(token, 2, 4) {
+                year = Some(y);
+                continue;
+            }
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let (day, month, mut year) = (day?, month?, year?);
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if year <= 69 {
+        year += 2000;
+    }
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let days = days_from_civil(i64::from(year), month, day);
+    let seconds = days * 86400 + i64::from(hour * 3600 + minute * 60 + second);
+    if seconds <= 0 {
+        Some(UNIX_EPOCH)
+    } else {
+        UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+    }
+}
+
+/// Parses a `hh:mm:ss` time, where each field has one or two digits.
+fn parse_time(token: &str) -> Option<(u32, u32, u32)> {
+    let mut fields = token.splitn(3, ':');
+    let hour = fields.next()?;
+    let minute = fields.next()?;
+    let second = fields.next()?;
+    if hour.is_empty() || hour.len() > 2 || !hour.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if minute.is_empty() || minute.len() > 2 || !minute.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((
+        hour.parse().ok()?,
+        minute.parse().ok()?,
+        leading_digits(second, 1, 2)?,
+    ))
+}
+
+/// Parses the `min` to `max` digits `token` starts with, as long as
+/// they aren't followed by more digits.
+fn leading_digits(token: &str, min: usize, max: usize) -> Option<u32> {
+    let len = token.bytes().take_while(u8::is_ascii_digit).count();
+    if len < min || len > max {
+        return None;
+    }
+    token[..len].parse().ok()
+}
+
+/// Parses a month from the first three letters of its English name.
+fn parse_month(token: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let prefix = token.get(..3)?.to_ascii_lowercase();
+    MONTHS
+        .iter()
+        .position(|m| *m == prefix)
+        .map(|idx| idx as u32 + 1)
+}
+
+/// Returns the number of days between 1970-01-01 and the given date
+/// of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month_of_year = i64::from((month + 9) % 12);
+    let day_of_year = (153 * month_of_year + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
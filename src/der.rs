@@ -0,0 +1,145 @@
+const BOOLEAN: u8 = 0x01;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const OBJECT_IDENTIFIER: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+
+/// The object identifier of the subject alternative name extension,
+/// 2.5.29.17.
+const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1D, 0x11];
+
+/// A DER element: its tag, its contents, and the whole element
+/// including the tag and length.
+pub(crate) struct Element<'a> {
+    pub(crate) tag: u8,
+    pub(crate) contents: &'a [u8],
+    pub(crate) raw: &'a [u8],
+}
+
+/// Reads the element at the start of `input`, returning it and the
+/// rest of `input`. Only single byte tags are supported, which is all
+/// certificates use.
+pub(crate) fn read(input: &[u8]) -> Option<(Element<'_>, &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    if tag & 0x1F == 0x1F {
+        return None;
+    }
+    let (&first, mut rest) = rest.split_first()?;
+    let len = if first < 0x80 {
+        usize::from(first)
+    } else {
+        let count = usize::from(first & 0x7F);
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let (bytes, after) = rest.split_at(count);
+        rest = after;
+        bytes.iter().fold(0, |len, &b| (len << 8) | usize::from(b))
+    };
+    if rest.len() < len {
+        return None;
+    }
+    let header_len = input.len() - rest.len();
+    let element = Element {
+        tag,
+        contents: &rest[..len],
+        raw: &input[..header_len + len],
+    };
+    Some((element, &rest[len..]))
+}
+
+/// Reads the element at the start of `input`, if it has `tag`.
+fn expect(input: &[u8], tag: u8) -> Option<(Element<'_>, &[u8])> {
+    read(input).filter(|(element, _)| element.tag == tag)
+}
+
+/// Returns the fields of the `TBSCertificate` of a DER-encoded
+/// certificate, starting at its serial number. See
+/// [RFC 5280](https://tools.ietf.org/html/rfc5280#section-4.1) for
+/// the structure of certificates.
+fn tbs_fields(certificate: &[u8]) -> Option<&[u8]> {
+    let (certificate, _) = expect(certificate, SEQUENCE)?;
+    let (tbs, _) = expect(certificate.contents, SEQUENCE)?;
+    let fields = tbs.contents;
+    // The version is explicitly tagged [0], and may be left out.
+    match expect(fields, 0xA0) {
+        Some((_, rest)) => Some(rest),
+        None => Some(fields),
+    }
+}
+
+/// Returns the DER-encoded `TBSCertificate` of a DER-encoded
+/// certificate, and the signature of its issuer over it.
+pub(crate) fn signed_data(certificate: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (certificate, _) = expect(certificate, SEQUENCE)?;
+    let (tbs, rest) = expect(certificate.contents, SEQUENCE)?;
+    // Skip the signature algorithm.
+    let (_, rest) = expect(rest, SEQUENCE)?;
+    let (signature, _) = expect(rest, BIT_STRING)?;
+    // Signatures are whole bytes, so no bits are unused.
+    match signature.contents.split_first() {
+        Some((0, signature)) => Some((tbs.raw, signature)),
+        _ => None,
+    }
+}
+
+/// Returns the DER-encoded `SubjectPublicKeyInfo` of a DER-encoded
+/// certificate.
+pub(crate) fn subject_public_key_info(certificate: &[u8]) -> Option<&[u8]> {
+    let mut fields = tbs_fields(certificate)?;
+    // Skip the serial number, signature algorithm, issuer, validity
+    // and subject.
+    for _ in 0..5 {
+        fields = read(fields)?.1;
+    }
+    let (spki, _) = expect(fields, SEQUENCE)?;
+    Some(spki.raw)
+}
+
+/// Returns the IP addresses among the subject alternative names of a
+/// DER-encoded certificate, as 4 or 16 bytes each.
+pub(crate) fn subject_alt_ip_addresses(certificate: &[u8]) -> Vec<&[u8]> {
+    let mut addresses = Vec::new();
+    let names = extension(certificate, SUBJECT_ALT_NAME).and_then(|value| expect(value, SEQUENCE));
+    if let Some((names, _)) = names {
+        let mut names = names.contents;
+        while let Some((name, rest)) = read(names) {
+            // An iPAddress is implicitly tagged [7].
+            if name.tag == 0x87 {
+                addresses.push(name.contents);
+            }
+            names = rest;
+        }
+    }
+    addresses
+}
+
+/// Returns the value of the extension of a DER-encoded certificate
+/// identified by `oid`, if it has one.
+fn extension<'a>(certificate: &'a [u8], oid: &[u8]) -> Option<&'a [u8]> {
+    let mut fields = tbs_fields(certificate)?;
+    // Skip everything up to and including the public key.
+    for _ in 0..6 {
+        fields = read(fields)?.1;
+    }
+    // The extensions are explicitly tagged [3], and may follow the
+    // issuer and subject unique IDs.
+    let (extensions, _) = loop {
+        let (element, rest) = read(fields)?;
+        if element.tag == 0xA3 {
+            break expect(element.contents, SEQUENCE)?;
+        }
+        fields = rest;
+    };
+    let mut extensions = extensions.contents;
+    while let Some((extension, rest)) = expect(extensions, SEQUENCE) {
+        let (id, value) = expect(extension.contents, OBJECT_IDENTIFIER)?;
+        if id.contents == oid {
+            // The critical flag defaults to false, and may be left out.
+            let value = expect(value, BOOLEAN).map_or(value, |(_, rest)| rest);
+            return expect(value, OCTET_STRING).map(|(value, _)| value.contents);
+        }
+        extensions = rest;
+    }
+    None
+}
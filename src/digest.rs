@@ -0,0 +1,255 @@
+use crate::hash::{hex, md5, random_hex, sha256};
+use crate::http::{Method, Request, Response};
+use crate::url::Url;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+/// Credentials for HTTP Digest authentication, as described in
+/// [RFC 7616](https://tools.ietf.org/html/rfc7616), along with the
+/// last challenge received from each origin. Requests sharing the
+/// credentials answer the challenge of the origin they're sent to
+/// right away, counting their uses of its nonce, instead of waiting
+/// for another `401`. Requests to other origins wait for a challenge
+/// of their own, so that nothing derived from the password is sent
+/// to servers that didn't ask for it.
+pub(crate) struct DigestAuth {
+    username: String,
+    password: String,
+    challenges: Mutex<HashMap<String, Challenge>>,
+}
+
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Algorithm,
+    session: bool,
+    qop: bool,
+    nc: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Sha256,
+}
+
+impl Algorithm {
+    fn hash(self, data: &str) -> String {
+        match self {
+            Algorithm::Md5 => hex(&md5(data.as_bytes())),
+            Algorithm::Sha256 => hex(&sha256(data.as_bytes())),
+        }
+    }
+}
+
+impl DigestAuth {
+    pub(crate) fn new(username: String, password: String) -> DigestAuth {
+        DigestAuth {
+            username,
+            password,
+            challenges: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Adds the `Authorization` header answering the last challenge
+    /// from the origin of `url` to `request`, if one has been
+    /// received.
+    pub(crate) fn authorize(&self, request: Request, url: &Url) -> Request {
+        match self.authorization(&request.method, url) {
+            Some(authorization) => request.with_header("Authorization", authorization),
+            None => request,
+        }
+    }
+
+    /// Stores the challenge of a `401` response for the origin it came
+    /// from, returning false if it has no Digest challenge that can be
+    /// answered. When several are offered, SHA-256 is preferred over
+    /// MD5.
+    pub(crate) fn update(&self, response: &Response) -> bool {
+        let challenge = response
+            .header_values("WWW-Authenticate")
+            .into_iter()
+            .flat_map(parse_challenges)
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Digest"))
+            .filter_map(|(_, params)| Challenge::from_params(&params))
+            .max_by_key(|challenge| challenge.algorithm == Algorithm::Sha256);
+        match (challenge, self.challenges.lock()) {
+            (Some(challenge), Ok(mut challenges)) => {
+                challenges.insert(origin(response.url()), challenge);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn authorization(&self, method: &Method, url: &Url) -> Option<String> {
+        let mut challenges = self.challenges.lock().ok()?;
+        let challenge = challenges.get_mut(&origin(url))?;
+        challenge.nc += 1;
+        let nc = format!("{:08x}", challenge.nc);
+        let cnonce = random_hex();
+        let uri = url.resource();
+        let hash = |data: String| challenge.algorithm.hash(&data);
+
+        let mut ha1 = hash(format!(
+            "{}:{}:{}",
+            self.username, challenge.realm, self.password
+        ));
+        if challenge.session {
+            ha1 = hash(format!("{}:{}:{}", ha1, challenge.nonce, cnonce));
+        }
+        let ha2 = hash(format!("{}:{}", method, uri));
+        let response = if challenge.qop {
+            hash(format!(
+                "{}:{}:{}:{}:auth:{}",
+                ha1, challenge.nonce, nc, cnonce, ha2
+            ))
+        } else {
+            hash(format!("{}:{}:{}", ha1, challenge.nonce, ha2))
+        };
+
+        let algorithm = match (challenge.algorithm, challenge.session) {
+            (Algorithm::Md5, false) => "MD5",
+            (Algorithm::Md5, true) => "MD5-sess",
+            (Algorithm::Sha256, false) => "SHA-256",
+            (Algorithm::Sha256, true) => "SHA-256-sess",
+        };
+        let mut authorization = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+            quote(&self.username),
+            quote(&challenge.realm),
+            quote(&challenge.nonce),
+            quote(&uri),
+            algorithm,
+            response
+        );
+        if challenge.qop {
+            authorization += &format!(", qop=auth, nc={}, cnonce=\"{}\"", nc, cnonce);
+        }
+        if let Some(ref opaque) = challenge.opaque {
+            authorization += &format!(", opaque=\"{}\"", quote(opaque));
+        }
+        Some(authorization)
+    }
+}
+
+impl fmt::Debug for DigestAuth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DigestAuth {{ username: {:?} }}", self.username)
+    }
+}
+
+impl Challenge {
+    /// Reads the parameters of a Digest challenge, returning `None`
+    /// if it uses an unsupported algorithm or quality of protection.
+    fn from_params(params: &[(String, String)]) -> Option<Challenge> {
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+        };
+        let (algorithm, session) = match param("algorithm") {
+            None => (Algorithm::Md5, false),
+            Some(a) if a.eq_ignore_ascii_case("MD5") => (Algorithm::Md5, false),
+            Some(a) if a.eq_ignore_ascii_case("MD5-sess") => (Algorithm::Md5, true),
+            Some(a) if a.eq_ignore_ascii_case("SHA-256") => (Algorithm::Sha256, false),
+            Some(a) if a.eq_ignore_ascii_case("SHA-256-sess") => (Algorithm::Sha256, true),
+            Some(_) => return None,
+        };
+        // Only `auth` is supported, but servers from before RFC 2617
+        // don't send a qop at all.
+        let qop = match param("qop") {
+            Some(qop) => {
+                if !qop
+                    .split(',')
+                    .any(|q| q.trim().eq_ignore_ascii_case("auth"))
+                {
+                    return None;
+                }
+                true
+            }
+            None => false,
+        };
+        Some(Challenge {
+            realm: param("realm").unwrap_or("").to_string(),
+            nonce: param("nonce")?.to_string(),
+            opaque: param("opaque").map(String::from),
+            algorithm,
+            session,
+            qop,
+            nc: 0,
+        })
+    }
+}
+
+type Params = Vec<(String, String)>;
+
+/// Splits a `WWW-Authenticate` header into its challenges: the
+/// authentication scheme, followed by its parameters.
+fn parse_challenges(header: &str) -> Vec<(String, Params)> {
+    let mut challenges: Vec<(String, Params)> = Vec::new();
+    let mut chars = header.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|&c| c == ',' || c.is_whitespace()) {
+            chars.next();
+        }
+        let mut token = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == '=' || c == ',' || c.is_whitespace() {
+                break;
+            }
+            token.push(c);
+            chars.next();
+        }
+        if token.is_empty() {
+            return challenges;
+        }
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek() != Some(&'=') {
+            challenges.push((token, Vec::new()));
+            continue;
+        }
+        chars.next();
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => value.extend(chars.next()),
+                    c => value.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == ',' || c.is_whitespace() {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+        }
+        if let Some((_, params)) = challenges.last_mut() {
+            params.push((token, value));
+        }
+    }
+}
+
+/// Returns the scheme, host and port of `url`, which identify the
+/// server a challenge came from.
+fn origin(url: &Url) -> String {
+    format!("{}://{}:{}", url.scheme(), url.host(), url.port())
+}
+
+/// Escapes `s` for use in a quoted string.
+fn quote(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
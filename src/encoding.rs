@@ -0,0 +1,91 @@
+/// Encodes `s` as a component of an `application/x-www-form-urlencoded`
+/// string: alphanumerics and `*-._` are kept as-is, spaces become `+`,
+/// and every other byte is percent-encoded.
+pub(crate) fn form_urlencode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for &byte in s.as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            _ => encoded += &format!("%{:02X}", byte),
+        }
+    }
+    encoded
+}
+
+/// Encodes `pairs` into an `application/x-www-form-urlencoded` string,
+/// ie. `key=value` pairs joined by `&`.
+pub(crate) fn form_urlencode_pairs<'a, I>(pairs: I) -> String
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    pairs
+        .into_iter()
+        .map(|(k, v)| format!("{}={}", form_urlencode(k), form_urlencode(v)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// Percent-encodes the bytes of `s` that can never appear in a URL,
+/// ie. control characters, spaces, non-ASCII characters and
+/// `"<>\^`{|}`, leaving everything else (including existing
+/// percent-encodings) untouched.
+pub(crate) fn percent_encode_invalid(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for &byte in s.as_bytes() {
+        if byte <= b' ' || byte >= 0x7F || b"\"<>\\^`{|}".contains(&byte) {
+            encoded += &format!("%{:02X}", byte);
+        } else {
+            encoded.push(byte as char);
+        }
+    }
+    encoded
+}
+
+/// Decodes the percent-encoded bytes in `s`. Invalid encodings are
+/// kept as-is, and invalid UTF-8 is replaced.
+pub(crate) fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = s
+            .get(i + 1..i + 3)
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) if bytes[i] == b'%' => {
+                decoded.push(byte);
+                i += 3;
+            }
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Encodes `bytes` as standard, padded base64.
+pub(crate) fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).cloned().unwrap_or(0),
+            chunk.get(2).cloned().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3F] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
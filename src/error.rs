@@ -0,0 +1,91 @@
+use crate::url::ParseError;
+use std::error;
+use std::fmt;
+use std::io;
+
+/// The errors that can occur while building a request, sending it,
+/// or handling its response.
+#[derive(Debug)]
+pub enum Error {
+    /// An error from reading or writing the connection, see
+    /// [`std::io::Error`](https://doc.rust-lang.org/std/io/struct.Error.html).
+    IoError(io::Error),
+    /// The URL of the request couldn't be parsed.
+    InvalidUrl(ParseError),
+    /// The request or response body couldn't be serialized into or
+    /// deserialized from JSON.
+    #[cfg(feature = "json")]
+    SerdeJsonError(serde_json::Error),
+    /// The proxy refused to connect to the server, eg. because the
+    /// proxy credentials were rejected.
+    ProxyError(String),
+    /// The TLS configuration is invalid, eg. a certificate or private
+    /// key couldn't be parsed.
+    #[cfg(feature = "https")]
+    TlsError(String),
+    /// None of the certificates the server presented has a public
+    /// key pinned with
+    /// [`TlsConfig::with_pinned_public_key`](struct.TlsConfig.html#method.with_pinned_public_key).
+    /// Contains the host of the server.
+    #[cfg(feature = "https")]
+    PinnedKeyMismatch(String),
+    /// The request was redirected more times than the
+    /// [`RedirectPolicy`](enum.RedirectPolicy.html) allows.
+    TooManyRedirects(usize),
+    /// The request was redirected back to this URL, which it had
+    /// already been redirected from.
+    RedirectLoop(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::IoError(ref err) => write!(f, "{}", err),
+            Error::InvalidUrl(ref err) => write!(f, "invalid URL: {}", err),
+            #[cfg(feature = "json")]
+            Error::SerdeJsonError(ref err) => write!(f, "{}", err),
+            Error::ProxyError(ref err) => write!(f, "proxy error: {}", err),
+            #[cfg(feature = "https")]
+            Error::TlsError(ref err) => write!(f, "TLS error: {}", err),
+            #[cfg(feature = "https")]
+            Error::PinnedKeyMismatch(ref host) => {
+                write!(f, "no pinned public key in the certificates of {}", host)
+            }
+            Error::TooManyRedirects(max) => write!(f, "more than {} redirects", max),
+            Error::RedirectLoop(ref url) => write!(f, "redirect loop at {}", url),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::IoError(ref err) => Some(err),
+            Error::InvalidUrl(ref err) => Some(err),
+            #[cfg(feature = "json")]
+            Error::SerdeJsonError(ref err) => Some(err),
+            #[cfg(feature = "https")]
+            Error::TlsError(_) | Error::PinnedKeyMismatch(_) => None,
+            Error::ProxyError(_) | Error::TooManyRedirects(_) | Error::RedirectLoop(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::IoError(err)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Error {
+        Error::InvalidUrl(err)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::SerdeJsonError(err)
+    }
+}
@@ -1,13 +1,29 @@
+#[cfg(feature = "compression")]
+use crate::body::decompress;
+use crate::body::{Body, BodyEnd, BodyReader, ChunkedReader, LengthReader};
 use crate::connection::Connection;
+use crate::cookies::CookieJar;
+use crate::digest::DigestAuth;
+use crate::encoding::{base64_encode, form_urlencode, form_urlencode_pairs, percent_decode};
+use crate::error::Error;
+use crate::multipart::Multipart;
+use crate::pool::{Pool, PooledStream};
+use crate::proxy::Proxy;
+use crate::redirect::{Redirect, RedirectPolicy};
+#[cfg(feature = "https")]
+use crate::tls::{TlsConfig, TlsInfo};
+use crate::url::{ParseError, Url};
+#[cfg(feature = "json")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "json")]
+use serde::Serialize;
 use std::collections::HashMap;
 use std::fmt;
 use std::io::BufRead;
 use std::io::BufReader;
-use std::io::Error;
-
-/// A URL type for requests.
-pub type URL = String;
-
+use std::io::{self, ErrorKind, Read, Write};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// An HTTP Response Status
 #[derive(Clone, Debug)]
@@ -26,6 +42,7 @@ pub enum Status {
 
 impl Status {
     /// check is status is a success
+    #[allow(clippy::match_like_matches_macro)]
     pub fn is_success(&self) -> bool {
         match self {
             Status::Success(_) => true,
@@ -35,6 +52,7 @@ impl Status {
 }
 
 impl From<i32> for Status {
+    #[allow(clippy::manual_range_contains)]
     fn from(i: i32) -> Self {
         if i >= 100 && i < 200 {
             Status::Info(i)
@@ -117,13 +135,21 @@ impl fmt::Display for Method {
 /// An HTTP request.
 #[derive(Clone, Debug)]
 pub struct Request {
-    method: Method,
-    pub(crate) host: URL,
-    pub(crate) resource: URL,
+    pub(crate) method: Method,
+    pub(crate) url: Result<Url, ParseError>,
     headers: HashMap<String, String>,
-    pub(crate) body: Option<String>,
+    pub(crate) body: Option<Body>,
     pub(crate) timeout: Option<u64>,
-    pub(crate) https: bool,
+    #[cfg(feature = "compression")]
+    pub(crate) compression: bool,
+    pub(crate) pool: Option<Arc<Pool>>,
+    pub(crate) cookie_jar: Option<Arc<CookieJar>>,
+    pub(crate) proxy: Option<Proxy>,
+    pub(crate) digest_auth: Option<Arc<DigestAuth>>,
+    pub(crate) redirect_policy: RedirectPolicy,
+    #[cfg(feature = "https")]
+    pub(crate) tls_config: Option<Arc<TlsConfig>>,
+    pub(crate) history: Vec<Redirect>,
 }
 
 impl Request {
@@ -131,86 +157,336 @@ impl Request {
     ///
     /// This is only the request's data, it is not sent yet. For
     /// sending the request, see [`send`](struct.Request.html#method.send).
-    pub fn new<T: Into<URL>>(method: Method, url: T) -> Request {
-        let (host, resource, https) = parse_url(url.into());
+    /// If `url` is not a valid [`Url`](struct.Url.html), sending the
+    /// request fails with [`Error::InvalidUrl`](enum.Error.html).
+    pub fn new<T: Into<String>>(method: Method, url: T) -> Request {
         Request {
             method,
-            host,
-            resource,
+            url: Url::parse(&url.into()),
             headers: HashMap::new(),
             body: None,
             timeout: None,
-            https,
+            #[cfg(feature = "compression")]
+            compression: true,
+            pool: None,
+            cookie_jar: None,
+            proxy: None,
+            digest_auth: None,
+            redirect_policy: RedirectPolicy::default(),
+            #[cfg(feature = "https")]
+            tls_config: None,
+            history: Vec::new(),
         }
     }
 
     /// Adds a header to the request this is called on. Use this
     /// function to add headers to your requests.
+    ///
+    /// Header names are case-insensitive, so this replaces any header
+    /// with the same name, regardless of its case.
     pub fn with_header<T: Into<String>, U: Into<String>>(mut self, key: T, value: U) -> Request {
-        self.headers.insert(key.into(), value.into());
+        let key = key.into();
+        self.remove_header(&key);
+        self.headers.insert(key, value.into());
         self
     }
 
     /// Adds headers to the request.
-    pub fn with_headers(mut self, headers: &HashMap<String, String>) -> Request {
-        for (k, v) in headers.iter() {
-            self.headers.insert(k.to_string(), v.to_string());
+    pub fn with_headers(self, headers: &HashMap<String, String>) -> Request {
+        headers.iter().fold(self, |request, (k, v)| {
+            request.with_header(k.as_str(), v.as_str())
+        })
+    }
+
+    /// Adds a query parameter to the URL of the request. Both `key`
+    /// and `value` are percent-encoded, and appended after any query
+    /// already present in the URL.
+    pub fn with_param<T: AsRef<str>, U: AsRef<str>>(mut self, key: T, value: U) -> Request {
+        if let Ok(ref mut url) = self.url {
+            url.append_query(&format!(
+                "{}={}",
+                form_urlencode(key.as_ref()),
+                form_urlencode(value.as_ref())
+            ));
         }
         self
     }
-    /// Sets the request body.
-    pub fn with_body<T: Into<String>>(mut self, body: T) -> Request {
+
+    /// Adds query parameters to the URL of the request, like
+    /// [`with_param`](#method.with_param).
+    pub fn with_params<I, T, U>(self, params: I) -> Request
+    where
+        I: IntoIterator<Item = (T, U)>,
+        T: AsRef<str>,
+        U: AsRef<str>,
+    {
+        params
+            .into_iter()
+            .fold(self, |request, (key, value)| request.with_param(key, value))
+    }
+
+    /// Sets the request body. Anything that converts into bytes can
+    /// be used, eg. `&str`, `String`, `&[u8]` or `Vec<u8>`.
+    pub fn with_body<T: Into<Vec<u8>>>(mut self, body: T) -> Request {
         let body = body.into();
         let body_length = body.len();
-        self.body = Some(body);
+        self.body = Some(Body::Bytes(body));
+        self.remove_header("Transfer-Encoding");
         self.with_header("Content-Length", format!("{}", body_length))
     }
 
+    /// Sets the request body to be streamed from `reader` while the
+    /// request is being sent, instead of being held in memory.
+    ///
+    /// If the `length` of the body is known, it is sent as the
+    /// `Content-Length` and exactly that many bytes are read from
+    /// `reader`. Otherwise the body is sent with
+    /// `Transfer-Encoding: chunked` until `reader` reaches EOF.
+    ///
+    /// Note that a streamed body can only be sent once, so it is not
+    /// resent when following redirects.
+    pub fn with_body_reader<R: Read + Send + 'static>(
+        mut self,
+        reader: R,
+        length: Option<u64>,
+    ) -> Request {
+        self.body = Some(Body::Reader(BodyReader::new(reader, length)));
+        match length {
+            Some(length) => {
+                self.remove_header("Transfer-Encoding");
+                self.with_header("Content-Length", format!("{}", length))
+            }
+            None => {
+                self.remove_header("Content-Length");
+                self.with_header("Transfer-Encoding", "chunked")
+            }
+        }
+    }
+
+    /// Sets the request body to the percent-encoded `pairs`, along with
+    /// the `application/x-www-form-urlencoded` `Content-Type` header.
+    pub fn with_form<K: AsRef<str>, V: AsRef<str>>(self, pairs: &[(K, V)]) -> Request {
+        let body = form_urlencode_pairs(pairs.iter().map(|(k, v)| (k.as_ref(), v.as_ref())));
+        self.with_form_body(body)
+    }
+
+    /// Like [`with_form`](#method.with_form), but takes the form's
+    /// fields from a map.
+    pub fn with_form_map(self, form: &HashMap<String, String>) -> Request {
+        let body = form_urlencode_pairs(form.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        self.with_form_body(body)
+    }
+
+    /// Sets the request body to `body` serialized as JSON, along with
+    /// the `application/json` `Content-Type` header.
+    #[cfg(feature = "json")]
+    pub fn with_json<T: Serialize>(mut self, body: &T) -> Result<Request, Error> {
+        let body = serde_json::to_vec(body)?;
+        self.remove_header("Content-Type");
+        Ok(self
+            .with_header("Content-Type", "application/json")
+            .with_body(body))
+    }
+
+    fn with_form_body(mut self, body: String) -> Request {
+        self.remove_header("Content-Type");
+        self.with_header("Content-Type", "application/x-www-form-urlencoded")
+            .with_body(body)
+    }
+
+    /// Sets the request body to a `multipart/form-data` form, along
+    /// with the matching `Content-Type` header. The form is streamed
+    /// as described in [`with_body_reader`](#method.with_body_reader).
+    pub fn with_multipart(mut self, form: Multipart) -> Request {
+        self.remove_header("Content-Type");
+        let content_type = form.content_type();
+        let (reader, length) = form.into_reader();
+        self.with_header("Content-Type", content_type)
+            .with_body_reader(reader, length)
+    }
+
+    /// Sets the `Authorization` header for HTTP Basic authentication
+    /// with `username` and `password`.
+    pub fn with_basic_auth<T: Into<String>, U: Into<String>>(
+        self,
+        username: T,
+        password: U,
+    ) -> Request {
+        let authorization = basic_auth(&username.into(), &password.into());
+        self.with_header("Authorization", authorization)
+    }
+
+    /// Sets the `Authorization` header to send `token` as a bearer
+    /// token, eg. an OAuth 2.0 access token.
+    pub fn with_bearer_token<T: Into<String>>(self, token: T) -> Request {
+        self.with_header("Authorization", format!("Bearer {}", token.into()))
+    }
+
+    /// Sets the credentials for HTTP Digest authentication. When the
+    /// server responds with a `401` and a Digest challenge, the
+    /// request is sent again, once, with an `Authorization` header
+    /// answering the challenge. Requests with a streamed body can't
+    /// be sent again, so they get the `401` response instead.
+    ///
+    /// To answer the challenge right away on later requests to the
+    /// same server, use
+    /// [`Client::with_digest_auth`](struct.Client.html#method.with_digest_auth).
+    pub fn with_digest_auth<T: Into<String>, U: Into<String>>(
+        mut self,
+        username: T,
+        password: U,
+    ) -> Request {
+        self.digest_auth = Some(Arc::new(DigestAuth::new(username.into(), password.into())));
+        self
+    }
+
+    /// Removes all headers named `key`, ignoring case.
+    pub(crate) fn remove_header(&mut self, key: &str) {
+        self.headers.retain(|k, _| !k.eq_ignore_ascii_case(key));
+    }
+
     /// Sets the request timeout.
     pub fn with_timeout(mut self, timeout: u64) -> Request {
         self.timeout = Some(timeout);
         self
     }
 
-    /// Sends this request to the host.
+    /// Sets whether the response should be transparently
+    /// decompressed. When enabled (the default), the request asks for
+    /// `gzip` or `deflate` encoded responses with an `Accept-Encoding`
+    /// header, and the response body is decoded according to its
+    /// `Content-Encoding`.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, compression: bool) -> Request {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets which redirects are followed, see
+    /// [`RedirectPolicy`](enum.RedirectPolicy.html).
+    pub fn with_redirect_policy(mut self, policy: RedirectPolicy) -> Request {
+        self.redirect_policy = policy;
+        self
+    }
+
+    /// Sends the request through `proxy`, instead of the proxy set by
+    /// the environment, if any. See [`Proxy`](struct.Proxy.html).
+    pub fn with_proxy(mut self, proxy: Proxy) -> Request {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Sets the TLS settings used if the request is sent over HTTPS,
+    /// see [`TlsConfig`](struct.TlsConfig.html).
     #[cfg(feature = "https")]
+    pub fn with_tls_config(mut self, tls_config: TlsConfig) -> Request {
+        self.tls_config = Some(Arc::new(tls_config));
+        self
+    }
+
+    /// Sends this request to the host.
     pub fn send(self) -> Result<Response, Error> {
-        if self.https {
-            Connection::new(self).send_https()
-        } else {
-            Connection::new(self).send()
+        let digest_auth = match self.digest_auth {
+            Some(ref digest_auth) => digest_auth.clone(),
+            None => return self.send_once(),
+        };
+        let url = self.url.clone()?;
+        let retry = match self.body {
+            Some(Body::Reader(_)) => None,
+            _ => Some(self.clone()),
+        };
+        let response = digest_auth.authorize(self, &url).send_once()?;
+        match retry {
+            Some(retry) if i32::from(&response.status) == 401 && digest_auth.update(&response) => {
+                digest_auth.authorize(retry, &url).send_once()
+            }
+            _ => Ok(response),
         }
     }
 
-    /// Sends this request to the host.
+    #[cfg(feature = "https")]
+    fn send_once(self) -> Result<Response, Error> {
+        Connection::new(self)?.send()
+    }
+
     #[cfg(not(feature = "https"))]
-    pub fn send(self) -> Result<Response, Error> {
-        if self.https {
+    fn send_once(self) -> Result<Response, Error> {
+        let connection = Connection::new(self)?;
+        if connection.url.is_https() {
             panic!("Can't send requests to urls that start with https:// when the `https` feature is not enabled!")
         } else {
-            Connection::new(self).send()
+            connection.send()
+        }
+    }
+
+    /// Writes the HTTP request for `url` to `stream`, streaming the
+    /// body if it is a reader. If the request is sent through `proxy`,
+    /// the stream must be connected to the proxy, or to a tunnel
+    /// through it for HTTPS.
+    pub(crate) fn write_to<W: Write>(
+        mut self,
+        url: &Url,
+        proxy: Option<&Proxy>,
+        stream: &mut W,
+    ) -> Result<(), io::Error> {
+        let body = self.body.take();
+        stream.write_all(&self.head_bytes(url, proxy))?;
+        if let Some(body) = body {
+            body.write_to(stream)?;
         }
+        stream.flush()
     }
 
-    /// Returns the HTTP request as a `String`, ready to be sent to
-    /// the server.
-    pub(crate) fn into_string(self) -> String {
+    /// Returns the request line and headers of the HTTP request as
+    /// bytes, ready to be sent to the server.
+    fn head_bytes(self, url: &Url, proxy: Option<&Proxy>) -> Vec<u8> {
         let mut http = String::new();
+        // Plain HTTP proxies need the whole URL in the request line,
+        // while HTTPS requests go through a tunnel to the host.
+        let proxy = proxy.filter(|p| p.is_http() && !url.is_https());
+        let target = match proxy {
+            Some(_) => format!("{}://{}{}", url.scheme(), url.host_header(), url.resource()),
+            None => url.resource(),
+        };
         // Add the request line and the "Host" header
         http += &format!(
             "{} {} HTTP/1.1\r\nHost: {}\r\n",
-            self.method, self.resource, self.host
+            self.method,
+            target,
+            url.host_header()
         );
+        if let Some(authorization) = proxy.and_then(Proxy::authorization) {
+            http += &format!("Proxy-Authorization: {}\r\n", authorization);
+        }
+        // Credentials in the URL are sent with Basic authentication,
+        // unless the request has its own authorization.
+        if let Some(username) = url.username() {
+            if find_header(&self.headers, "Authorization").is_none() {
+                let password = url.password().unwrap_or("");
+                let authorization =
+                    basic_auth(&percent_decode(username), &percent_decode(password));
+                http += &format!("Authorization: {}\r\n", authorization);
+            }
+        }
+        #[cfg(feature = "compression")]
+        {
+            if self.compression && find_header(&self.headers, "Accept-Encoding").is_none() {
+                http += "Accept-Encoding: gzip, deflate\r\n";
+            }
+        }
+        if let Some(ref jar) = self.cookie_jar {
+            if find_header(&self.headers, "Cookie").is_none() {
+                if let Some(cookies) = jar.cookie_header(url) {
+                    http += &format!("Cookie: {}\r\n", cookies);
+                }
+            }
+        }
         // Add other headers
         for (k, v) in self.headers {
             http += &format!("{}: {}\r\n", k, v);
         }
-        // Add the body
         http += "\r\n";
-        if let Some(body) = self.body {
-            http += &body;
-        }
-        http
+        http.into_bytes()
     }
 }
 
@@ -220,19 +496,30 @@ pub struct Response {
     pub status: Status,
     /// The reason phrase of the response, eg. "Not Found".
     pub reason_phrase: String,
-    /// The headers of the response.
+    /// The headers of the response. If a header appears more than
+    /// once, only one of its values is kept here, see
+    /// [`header_values`](#method.header_values) for all of them.
     pub headers: HashMap<String, String>,
     /// The body of the response.
-    pub body: Box<BufRead>,
+    pub body: Box<dyn BufRead>,
+    raw_headers: Vec<(String, String)>,
+    url: Url,
+    history: Vec<Redirect>,
+    #[cfg(feature = "https")]
+    tls_info: Option<TlsInfo>,
 }
 
 impl Response {
-    pub(crate) fn from_stream<T: std::io::Read + 'static>(stream: T) -> std::io::Result<Response> {
-        let mut stream = BufReader::new(stream);
+    pub(crate) fn from_stream(
+        mut stream: PooledStream,
+        url: &Url,
+        request: &Request,
+    ) -> std::io::Result<Response> {
         // get http status line
         let mut s = String::new();
         stream.read_line(&mut s)?;
         let (status, reason_phrase) = parse_status_line(&s);
+        let http_1_0 = s.starts_with("HTTP/1.0");
         // get http headers
         let mut buf: Vec<String> = Vec::new();
         loop {
@@ -245,7 +532,7 @@ impl Response {
             }
         }
 
-        let headers: HashMap<String, String> = buf
+        let raw_headers: Vec<(String, String)> = buf
             .iter()
             .map(|elem| {
                 let idx = elem.find(':').unwrap();
@@ -253,52 +540,196 @@ impl Response {
                 (key.to_string(), value[1..].trim().to_string())
             })
             .collect();
+        let headers: HashMap<String, String> = raw_headers.iter().cloned().collect();
+
+        #[cfg(feature = "https")]
+        let tls_info = stream.tls_info();
+        if is_keep_alive(http_1_0, &headers) {
+            stream.set_keep_alive(keep_alive_timeout(&headers));
+        }
+
+        let body: Box<dyn BufRead> = if !has_body(&request.method, &status) {
+            stream.end_of_body();
+            Box::new(io::empty())
+        } else if is_chunked(&headers) {
+            Box::new(BufReader::new(ChunkedReader::new(stream)))
+        } else if let Some(length) = content_length(&headers)? {
+            if length == 0 {
+                stream.end_of_body();
+            }
+            Box::new(BufReader::new(LengthReader::new(stream, length)))
+        } else {
+            // Without any framing, the body ends when the server
+            // closes the connection.
+            Box::new(stream)
+        };
+        // Responses without a body, or with an empty one, have nothing
+        // to decompress.
+        #[cfg(feature = "compression")]
+        let body = if request.compression
+            && has_body(&request.method, &status)
+            && (is_chunked(&headers) || content_length(&headers)? != Some(0))
+        {
+            decompress(body, find_header(&headers, "Content-Encoding"))
+        } else {
+            body
+        };
 
         let resp = Response {
             status,
             reason_phrase,
             headers,
-            body: Box::new(stream),
+            body,
+            raw_headers,
+            url: url.clone(),
+            history: request.history.clone(),
+            #[cfg(feature = "https")]
+            tls_info,
         };
 
         Ok(resp)
     }
+
+    /// Returns the values of every header named `name`, ignoring
+    /// case, in the order they were received. Unlike
+    /// [`headers`](#structfield.headers), this includes all of the
+    /// values of headers that appear more than once, like
+    /// `Set-Cookie`.
+    pub fn header_values(&self, name: &str) -> Vec<&str> {
+        self.raw_headers
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    /// Returns the URL the response came from, which is the URL of
+    /// the request unless redirects were followed.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns the redirects that were followed to get to this
+    /// response, in order, starting with the URL of the request.
+    pub fn history(&self) -> &[Redirect] {
+        &self.history
+    }
+
+    /// Returns the details of the TLS session the response was
+    /// received over, if it was received over HTTPS.
+    #[cfg(feature = "https")]
+    pub fn tls_info(&self) -> Option<&TlsInfo> {
+        self.tls_info.as_ref()
+    }
+}
+
+impl Response {
+    /// Deserializes the body of the response from JSON, reading it
+    /// straight from the connection.
+    #[cfg(feature = "json")]
+    pub fn json<T: DeserializeOwned>(self) -> Result<T, Error> {
+        Ok(serde_json::from_reader(self.body)?)
+    }
 }
 
 impl fmt::Debug for Response {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Response{{ status_code: {}, reason_phrase: {}, headers: {:#?}, body: <BufRead> }}",
-            self.status, self.reason_phrase, self.headers
+            "Response{{ url: {}, status_code: {}, reason_phrase: {}, headers: {:#?}, body: <BufRead> }}",
+            self.url.without_credentials(),
+            self.status,
+            self.reason_phrase,
+            self.headers
         )
     }
 }
 
-pub(crate) fn parse_url(url: URL) -> (URL, URL, bool) {
-    let mut first = URL::new();
-    let mut second = URL::new();
-    let mut slashes = 0;
-    for c in url.chars() {
-        if c == '/' {
-            slashes += 1;
-        } else if slashes == 2 {
-            first.push(c);
-        }
-        if slashes >= 3 {
-            second.push(c);
-        }
+/// Looks up a header by name, ignoring ASCII case as header field
+/// names are case-insensitive.
+pub(crate) fn find_header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Returns the value of the `Authorization` header for HTTP Basic
+/// authentication.
+pub(crate) fn basic_auth(username: &str, password: &str) -> String {
+    let credentials = format!("{}:{}", username, password);
+    format!("Basic {}", base64_encode(credentials.as_bytes()))
+}
+
+/// Checks whether the last transfer coding applied to the body is
+/// `chunked`.
+fn is_chunked(headers: &HashMap<String, String>) -> bool {
+    match find_header(headers, "Transfer-Encoding") {
+        Some(codings) => codings
+            .rsplit(',')
+            .next()
+            .is_some_and(|c| c.trim().eq_ignore_ascii_case("chunked")),
+        None => false,
     }
-    // Ensure the resource is *something*
-    if second.is_empty() {
-        second += "/";
+}
+
+/// Parses the `Content-Length` header, if there is one.
+fn content_length(headers: &HashMap<String, String>) -> Result<Option<u64>, io::Error> {
+    match find_header(headers, "Content-Length") {
+        Some(length) => match length.trim().parse::<u64>() {
+            Ok(length) => Ok(Some(length)),
+            Err(_) => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid Content-Length: {:?}", length),
+            )),
+        },
+        None => Ok(None),
     }
-    // Set appropriate port
-    let https = url.starts_with("https://");
-    if !first.contains(':') {
-        first += if https { ":443" } else { ":80" };
+}
+
+/// Checks whether the server allows the connection to be reused
+/// after the response.
+fn is_keep_alive(http_1_0: bool, headers: &HashMap<String, String>) -> bool {
+    let has_option = |option: &str| match find_header(headers, "Connection") {
+        Some(connection) => connection
+            .split(',')
+            .any(|o| o.trim().eq_ignore_ascii_case(option)),
+        None => false,
+    };
+    if http_1_0 {
+        has_option("keep-alive")
+    } else {
+        !has_option("close")
     }
-    (first, second, https)
+}
+
+/// Parses the `timeout` parameter of the `Keep-Alive` header, the
+/// number of seconds the server will keep an idle connection open.
+fn keep_alive_timeout(headers: &HashMap<String, String>) -> Option<Duration> {
+    find_header(headers, "Keep-Alive")?
+        .split(',')
+        .filter_map(|param| {
+            let mut param = param.splitn(2, '=');
+            match (param.next(), param.next()) {
+                (Some(key), Some(value)) if key.trim().eq_ignore_ascii_case("timeout") => {
+                    value.trim().parse::<u64>().ok()
+                }
+                _ => None,
+            }
+        })
+        .next()
+        .map(Duration::from_secs)
+}
+
+/// Checks whether a response can have a body at all: responses to
+/// HEAD requests, 1XX, 204 and 304 responses never do, regardless
+/// of their headers.
+fn has_body(method: &Method, status: &Status) -> bool {
+    let code = i32::from(status);
+    !matches!(method, Method::Head)
+        && !matches!(status, Status::Info(_))
+        && code != 204
+        && code != 304
 }
 
 pub(crate) fn parse_status_line(line: &str) -> (Status, String) {
@@ -310,5 +741,8 @@ pub(crate) fn parse_status_line(line: &str) -> (Status, String) {
             }
         }
     }
-    (Status::from(503), "Server did not provide a status line".to_string())
+    (
+        Status::from(503),
+        "Server did not provide a status line".to_string(),
+    )
 }
@@ -15,37 +15,83 @@
 //! mrq = { version = "0.1.0", features = ["https"] }
 //! ```
 //!
+//! Servers are verified against the Mozilla root certificates by
+//! default. Other root certificates, including the ones trusted by
+//! the operating system, and client certificates can be set with a
+//! [`TlsConfig`](struct.TlsConfig.html).
+//!
+//! # Compression
+//!
+//! With the `compression` feature enabled, requests ask for `gzip` or
+//! `deflate` encoded responses, and response bodies are decompressed
+//! transparently. This can be turned off per request with
+//! [`with_compression(false)`](struct.Request.html#method.with_compression).
+//! ```toml
+//! mrq = { version = "0.1.0", features = ["compression"] }
+//! ```
+//!
+//! # JSON
+//!
+//! The `json` feature adds
+//! [`with_json`](struct.Request.html#method.with_json) for sending
+//! any [`serde`](https://crates.io/crates/serde) serializable value
+//! as the request body, and
+//! [`json`](struct.Response.html#method.json) for deserializing the
+//! response body.
+//! ```toml
+//! mrq = { version = "0.1.0", features = ["json"] }
+//! ```
+//!
+//! # Proxies
+//!
+//! Requests are sent through the HTTP or SOCKS5 proxy set with
+//! [`with_proxy`](struct.Request.html#method.with_proxy), or
+//! otherwise through the one in the `http_proxy`, `https_proxy` or
+//! `all_proxy` environment variable, unless the host is listed in
+//! `no_proxy`. HTTPS requests are tunnelled through HTTP proxies
+//! with `CONNECT`.
+//!
 //! # Examples
 //!
 //! ## Get
 //! ```no_run
 //! // This is a simple example of sending a GET request and
 //! // printing out the response.
-//! if let Ok(response) = mrq::get("http://httpbin.org/ip").send() {
-//!     println!("{}", response.body);
+//! use std::io::Read;
+//!
+//! if let Ok(mut response) = mrq::get("http://httpbin.org/ip").send() {
+//!     let mut body = String::new();
+//!     response.body.read_to_string(&mut body).unwrap();
+//!     println!("{}", body);
 //! }
 //! ```
 //!
 //! ## Body
 //! ```no_run
 //! // To include a body, add .with_body("") before .send().
-//! if let Ok(response) = mrq::post("http://httpbin.org/post")
+//! # use std::io::Read;
+//! if let Ok(mut response) = mrq::post("http://httpbin.org/post")
 //!     .with_body("Pong!")
 //!     .send()
 //! {
-//!     println!("{}", response.body);
+//!     let mut body = String::new();
+//!     response.body.read_to_string(&mut body).unwrap();
+//!     println!("{}", body);
 //! }
 //! ```
 //!
 //! ## Headers
 //! ```no_run
 //! // To add a header, add .with_header("Key", "Value") before .send().
-//! if let Ok(response) = mrq::get("http://httpbin.org/headers")
+//! # use std::io::Read;
+//! if let Ok(mut response) = mrq::get("http://httpbin.org/headers")
 //!     .with_header("Accept", "text/plain")
 //!     .with_header("Something", "Interesting")
 //!     .send()
 //! {
-//!     println!("{}", response.body);
+//!     let mut body = String::new();
+//!     response.body.read_to_string(&mut body).unwrap();
+//!     println!("{}", body);
 //! }
 //! ```
 //!
@@ -54,11 +100,14 @@
 //! // To avoid timing out, or limit the request's response time even more,
 //! // use .with_timeout(n) before .send(). The given value is in seconds.
 //! // NOTE: There is no timeout by default.
-//! if let Ok(response) = mrq::post("http://httpbin.org/delay/6")
+//! # use std::io::Read;
+//! if let Ok(mut response) = mrq::post("http://httpbin.org/delay/6")
 //!     .with_timeout(10)
 //!     .send()
 //! {
-//!     println!("{}", response.body);
+//!     let mut body = String::new();
+//!     response.body.read_to_string(&mut body).unwrap();
+//!     println!("{}", body);
 //! }
 //! ```
 //!
@@ -86,16 +135,49 @@
 
 #![deny(missing_docs)]
 
+#[cfg(feature = "compression")]
+extern crate flate2;
 #[cfg(feature = "https")]
 extern crate rustls;
+#[cfg(feature = "json")]
+extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "https")]
+extern crate untrusted;
 #[cfg(feature = "https")]
 extern crate webpki;
 #[cfg(feature = "https")]
 extern crate webpki_roots;
 
-mod requests;
-mod http;
+mod body;
+mod client;
 mod connection;
+mod cookies;
+#[cfg(feature = "https")]
+mod der;
+mod digest;
+mod encoding;
+mod error;
+mod hash;
+mod http;
+mod multipart;
+mod pool;
+mod proxy;
+mod redirect;
+mod requests;
+#[cfg(feature = "https")]
+mod tls;
+mod url;
 
-pub use requests::*;
+pub use client::*;
+pub use cookies::*;
+pub use error::*;
 pub use http::*;
+pub use multipart::*;
+pub use proxy::*;
+pub use redirect::*;
+pub use requests::*;
+#[cfg(feature = "https")]
+pub use tls::*;
+pub use url::*;
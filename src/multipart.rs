@@ -0,0 +1,263 @@
+use crate::hash::random_hex;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{Cursor, Error, Read};
+use std::path::Path;
+
+/// A `multipart/form-data` request body, attached to a request with
+/// [`with_multipart`](struct.Request.html#method.with_multipart).
+///
+/// The parts are streamed to the server as the request is sent, so
+/// files are never loaded into memory as a whole.
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), mrq::Error> {
+/// let form = mrq::Multipart::new()
+///     .with_text("title", "Holiday")
+///     .with_file("photo", "photos/beach.jpg")?
+///     .with_part(
+///         "notes",
+///         mrq::Part::bytes("Sunny.").with_content_type("text/markdown"),
+///     );
+/// let response = mrq::post("http://example.com/upload")
+///     .with_multipart(form)
+///     .send()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<(String, Part)>,
+}
+
+impl Multipart {
+    /// Creates an empty form with a randomly generated boundary.
+    pub fn new() -> Multipart {
+        Multipart {
+            boundary: generate_boundary(),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Adds a text field to the form.
+    pub fn with_text<T: Into<String>, U: Into<String>>(self, name: T, value: U) -> Multipart {
+        self.with_part(name, Part::text(value))
+    }
+
+    /// Adds the file at `path` to the form, with its file name
+    /// as the part's filename. The file is opened right away, but
+    /// only read while the request is being sent.
+    pub fn with_file<T: Into<String>, P: AsRef<Path>>(
+        self,
+        name: T,
+        path: P,
+    ) -> Result<Multipart, Error> {
+        Ok(self.with_part(name, Part::file(path)?))
+    }
+
+    /// Adds a part to the form.
+    pub fn with_part<T: Into<String>>(mut self, name: T, part: Part) -> Multipart {
+        self.parts.push((name.into(), part));
+        self
+    }
+
+    /// Returns the boundary separating the parts of the form.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Returns the value of the `Content-Type` header for this form.
+    pub(crate) fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Turns the form into a reader producing the encoded body, and
+    /// the body's length if all of the parts' lengths are known.
+    pub(crate) fn into_reader(self) -> (MultipartReader, Option<u64>) {
+        let mut reader = MultipartReader {
+            segments: VecDeque::new(),
+        };
+        let mut length = Some(0);
+
+        for (name, part) in self.parts {
+            let header = part.header(&self.boundary, &name).into_bytes();
+            length = length.map(|l| l + header.len() as u64 + 2);
+            reader.push_bytes(header);
+            match part.content {
+                Content::Bytes(bytes) => {
+                    length = length.map(|l| l + bytes.len() as u64);
+                    reader.push_bytes(bytes);
+                }
+                Content::Reader(content, Some(part_length)) => {
+                    length = length.map(|l| l + part_length);
+                    reader
+                        .segments
+                        .push_back(Box::new(content.take(part_length)));
+                }
+                Content::Reader(content, None) => {
+                    length = None;
+                    reader.segments.push_back(content);
+                }
+            }
+            reader.push_bytes(b"\r\n".to_vec());
+        }
+        let end = format!("--{}--\r\n", self.boundary).into_bytes();
+        length = length.map(|l| l + end.len() as u64);
+        reader.push_bytes(end);
+
+        (reader, length)
+    }
+}
+
+impl Default for Multipart {
+    fn default() -> Multipart {
+        Multipart::new()
+    }
+}
+
+impl fmt::Debug for Multipart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&String> = self.parts.iter().map(|(name, _)| name).collect();
+        write!(
+            f,
+            "Multipart {{ boundary: {:?}, parts: {:?} }}",
+            self.boundary, names
+        )
+    }
+}
+
+/// A single part of a [`Multipart`](struct.Multipart.html) form.
+pub struct Part {
+    content: Content,
+    filename: Option<String>,
+    content_type: Option<String>,
+}
+
+enum Content {
+    Bytes(Vec<u8>),
+    Reader(Box<dyn Read + Send>, Option<u64>),
+}
+
+impl Part {
+    /// Creates a part containing `value`.
+    pub fn text<T: Into<String>>(value: T) -> Part {
+        Part::bytes(value.into())
+    }
+
+    /// Creates a part containing `bytes`.
+    pub fn bytes<T: Into<Vec<u8>>>(bytes: T) -> Part {
+        Part {
+            content: Content::Bytes(bytes.into()),
+            filename: None,
+            content_type: None,
+        }
+    }
+
+    /// Creates a part that is streamed from `reader` when the request
+    /// is sent. If the `length` is known, exactly that many bytes are
+    /// read, and the request can be sent with a `Content-Length`.
+    pub fn reader<R: Read + Send + 'static>(reader: R, length: Option<u64>) -> Part {
+        Part {
+            content: Content::Reader(Box::new(reader), length),
+            filename: None,
+            content_type: None,
+        }
+    }
+
+    /// Creates a part streamed from the file at `path`, with its file
+    /// name as the part's filename and `application/octet-stream` as
+    /// its content type.
+    pub fn file<P: AsRef<Path>>(path: P) -> Result<Part, Error> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+        let part = Part::reader(file, Some(length)).with_content_type("application/octet-stream");
+        Ok(match path.file_name() {
+            Some(filename) => part.with_filename(filename.to_string_lossy()),
+            None => part,
+        })
+    }
+
+    /// Sets the filename sent in the part's `Content-Disposition`.
+    pub fn with_filename<T: Into<String>>(mut self, filename: T) -> Part {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Sets the part's `Content-Type`. Line breaks are removed, so
+    /// the value can't add headers of its own to the part.
+    pub fn with_content_type<T: Into<String>>(mut self, content_type: T) -> Part {
+        let mut content_type = content_type.into();
+        content_type.retain(|c| c != '\r' && c != '\n');
+        self.content_type = Some(content_type);
+        self
+    }
+
+    /// Returns the boundary line and headers preceding the content.
+    fn header(&self, boundary: &str, name: &str) -> String {
+        let mut header = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            boundary,
+            escape(name)
+        );
+        if let Some(ref filename) = self.filename {
+            header += &format!("; filename=\"{}\"", escape(filename));
+        }
+        header += "\r\n";
+        if let Some(ref content_type) = self.content_type {
+            header += &format!("Content-Type: {}\r\n", content_type);
+        }
+        header += "\r\n";
+        header
+    }
+}
+
+impl fmt::Debug for Part {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Part {{ filename: {:?}, content_type: {:?} }}",
+            self.filename, self.content_type
+        )
+    }
+}
+
+/// Reads the encoded form by reading its segments one after another.
+pub(crate) struct MultipartReader {
+    segments: VecDeque<Box<dyn Read + Send>>,
+}
+
+impl MultipartReader {
+    fn push_bytes(&mut self, bytes: Vec<u8>) {
+        self.segments.push_back(Box::new(Cursor::new(bytes)));
+    }
+}
+
+impl Read for MultipartReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        while let Some(segment) = self.segments.front_mut() {
+            let n = segment.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            self.segments.pop_front();
+        }
+        Ok(0)
+    }
+}
+
+/// Escapes a name or filename for use in a quoted string, the way
+/// browsers do.
+fn escape(s: &str) -> String {
+    s.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Generates a boundary that is very unlikely to appear in any of
+/// the parts.
+fn generate_boundary() -> String {
+    format!("------------------------{}", random_hex())
+}
@@ -0,0 +1,258 @@
+use crate::body::BodyEnd;
+use crate::connection::Stream;
+use crate::proxy::Proxy;
+#[cfg(feature = "https")]
+use crate::tls::{TlsConfig, TlsInfo};
+use crate::url::Url;
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "https")]
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Identifies the connections that can be used for a request: the
+/// scheme, host, port and proxy have to match, and for HTTPS the TLS
+/// settings too.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct PoolKey {
+    https: bool,
+    host: String,
+    port: u16,
+    proxy: Option<Proxy>,
+    #[cfg(feature = "https")]
+    tls_config: Option<TlsConfigRef>,
+}
+
+impl PoolKey {
+    pub(crate) fn new(url: &Url, proxy: Option<&Proxy>) -> PoolKey {
+        PoolKey {
+            https: url.is_https(),
+            host: url.host().to_string(),
+            port: url.port(),
+            proxy: proxy.cloned(),
+            #[cfg(feature = "https")]
+            tls_config: None,
+        }
+    }
+
+    /// Sets the TLS settings the connections were made with, if they
+    /// are HTTPS connections.
+    #[cfg(feature = "https")]
+    pub(crate) fn with_tls_config(mut self, tls_config: Option<&Arc<TlsConfig>>) -> PoolKey {
+        if self.https {
+            self.tls_config = tls_config.cloned().map(TlsConfigRef);
+        }
+        self
+    }
+}
+
+/// TLS settings compared by the identity of their rustls
+/// configuration, so that connections are only shared by requests
+/// with clones of the same settings, usually those of a
+/// [`Client`](struct.Client.html).
+#[cfg(feature = "https")]
+#[derive(Clone, Debug)]
+struct TlsConfigRef(Arc<TlsConfig>);
+
+#[cfg(feature = "https")]
+impl PartialEq for TlsConfigRef {
+    fn eq(&self, other: &TlsConfigRef) -> bool {
+        self.0.shares_config(&other.0)
+    }
+}
+
+#[cfg(feature = "https")]
+impl Eq for TlsConfigRef {}
+
+#[cfg(feature = "https")]
+impl Hash for TlsConfigRef {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.config_id().hash(state)
+    }
+}
+
+struct IdleStream {
+    stream: Stream,
+    expires: Instant,
+}
+
+/// A pool of idle keep-alive connections, shared by the requests sent
+/// with a [`Client`](struct.Client.html).
+pub(crate) struct Pool {
+    idle: Mutex<HashMap<PoolKey, Vec<IdleStream>>>,
+    pub(crate) max_idle_per_host: usize,
+    pub(crate) idle_timeout: Duration,
+}
+
+impl Pool {
+    pub(crate) fn new(max_idle_per_host: usize, idle_timeout: Duration) -> Pool {
+        Pool {
+            idle: Mutex::new(HashMap::new()),
+            max_idle_per_host,
+            idle_timeout,
+        }
+    }
+
+    /// Takes the most recently used idle connection for `key`, if
+    /// there is one that hasn't expired yet, nor been closed by the
+    /// server.
+    pub(crate) fn take(&self, key: &PoolKey) -> Option<Stream> {
+        let mut idle = self.idle.lock().ok()?;
+        reap(&mut idle);
+        let streams = idle.get_mut(key)?;
+        let mut stream = None;
+        while let Some(idle) = streams.pop() {
+            if !idle.stream.is_closed() {
+                stream = Some(idle.stream);
+                break;
+            }
+        }
+        if streams.is_empty() {
+            idle.remove(key);
+        }
+        stream
+    }
+
+    /// Returns a connection to the pool, to be reused until it has
+    /// been idle for `timeout`, or the pool's idle timeout if that's
+    /// shorter.
+    pub(crate) fn put(&self, key: PoolKey, stream: Stream, timeout: Option<Duration>) {
+        let timeout = match timeout {
+            Some(timeout) if timeout < self.idle_timeout => timeout,
+            _ => self.idle_timeout,
+        };
+        if self.max_idle_per_host == 0 || timeout == Duration::from_secs(0) {
+            return;
+        }
+        if let Ok(mut idle) = self.idle.lock() {
+            reap(&mut idle);
+            let streams = idle.entry(key).or_default();
+            if streams.len() >= self.max_idle_per_host {
+                streams.remove(0);
+            }
+            streams.push(IdleStream {
+                stream,
+                expires: Instant::now() + timeout,
+            });
+        }
+    }
+}
+
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let idle = match self.idle.lock() {
+            Ok(idle) => idle.values().map(Vec::len).sum(),
+            Err(_) => 0,
+        };
+        write!(
+            f,
+            "Pool {{ idle: {}, max_idle_per_host: {}, idle_timeout: {:?} }}",
+            idle, self.max_idle_per_host, self.idle_timeout
+        )
+    }
+}
+
+/// Drops the connections that have been idle for too long.
+// The TLS settings in the keys can't change what the keys hash to,
+// since only their address is hashed.
+#[allow(clippy::mutable_key_type)]
+fn reap(idle: &mut HashMap<PoolKey, Vec<IdleStream>>) {
+    let now = Instant::now();
+    for streams in idle.values_mut() {
+        streams.retain(|idle| idle.expires > now);
+    }
+    idle.retain(|_, streams| !streams.is_empty());
+}
+
+/// The connection a response is read from. If the connection came
+/// from a pool and the server allows it, the connection is returned
+/// to the pool once the response's body has been read completely.
+pub(crate) struct PooledStream {
+    reader: Option<BufReader<Stream>>,
+    pool: Option<(Arc<Pool>, PoolKey)>,
+    keep_alive: Option<Option<Duration>>,
+}
+
+impl PooledStream {
+    pub(crate) fn new(stream: Stream, pool: Option<(Arc<Pool>, PoolKey)>) -> PooledStream {
+        PooledStream {
+            reader: Some(BufReader::new(stream)),
+            pool,
+            keep_alive: None,
+        }
+    }
+
+    /// Allows the connection to be reused once the body has been
+    /// read, for at most `timeout` if the server specified one.
+    pub(crate) fn set_keep_alive(&mut self, timeout: Option<Duration>) {
+        self.keep_alive = Some(timeout);
+    }
+
+    /// Returns the details of the TLS session, for HTTPS connections.
+    #[cfg(feature = "https")]
+    pub(crate) fn tls_info(&self) -> Option<TlsInfo> {
+        self.reader.as_ref()?.get_ref().tls_info()
+    }
+
+    fn reader(&mut self) -> io::Result<&mut BufReader<Stream>> {
+        match self.reader {
+            Some(ref mut reader) => Ok(reader),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "the connection has been returned to the pool",
+            )),
+        }
+    }
+}
+
+impl BodyEnd for PooledStream {
+    fn end_of_body(&mut self) {
+        let timeout = match self.keep_alive {
+            Some(timeout) => timeout,
+            None => return,
+        };
+        if let (Some((pool, key)), Some(reader)) = (self.pool.take(), self.reader.take()) {
+            // Anything left in the buffer means the server sent more
+            // than the response, so the connection can't be trusted.
+            if reader.buffer().is_empty() {
+                pool.put(key, reader.into_inner(), timeout);
+            }
+        }
+    }
+}
+
+impl Read for PooledStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.reader {
+            Some(ref mut reader) => reader.read(buf),
+            None => Ok(0),
+        }
+    }
+}
+
+impl BufRead for PooledStream {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self.reader {
+            Some(ref mut reader) => reader.fill_buf(),
+            None => Ok(&[]),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Some(ref mut reader) = self.reader {
+            reader.consume(amt);
+        }
+    }
+}
+
+impl Write for PooledStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.reader()?.get_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.reader()?.get_mut().flush()
+    }
+}
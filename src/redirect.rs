@@ -0,0 +1,129 @@
+use crate::error::Error;
+use crate::http::Status;
+use crate::url::Url;
+use std::fmt;
+use std::sync::Arc;
+
+/// The most redirects followed for a request, whatever its policy.
+const MAX_REDIRECTS: usize = 50;
+
+/// The closure of a [`RedirectPolicy::Custom`](enum.RedirectPolicy.html#variant.Custom)
+/// policy.
+pub type RedirectFn = dyn Fn(&Url, &[Url]) -> bool + Send + Sync;
+
+/// Decides which redirects are followed, set with
+/// [`Request::with_redirect_policy`](struct.Request.html#method.with_redirect_policy)
+/// or [`Client::with_redirect_policy`](struct.Client.html#method.with_redirect_policy).
+///
+/// Only `301`, `302`, `303`, `307` and `308` responses with a
+/// `Location` header are redirects. The default policy follows up to
+/// 10 of them. No policy follows more than 50: sending the request
+/// fails with [`Error::TooManyRedirects`](enum.Error.html) after that.
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), mrq::Error> {
+/// // Only follow redirects within example.com.
+/// let policy = mrq::RedirectPolicy::custom(|next, _previous| next.host() == "example.com");
+/// let response = mrq::get("http://example.com")
+///     .with_redirect_policy(policy)
+///     .send()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub enum RedirectPolicy {
+    /// Redirects aren't followed, the redirect response is returned
+    /// instead.
+    None,
+    /// Up to this many redirects are followed. Sending the request
+    /// fails with [`Error::TooManyRedirects`](enum.Error.html) if
+    /// there are more.
+    Limited(usize),
+    /// The closure is called with the URL of the next redirect and
+    /// the URLs redirected from so far, and returns whether to follow
+    /// the redirect. If it doesn't, the redirect response is returned.
+    Custom(Arc<RedirectFn>),
+}
+
+impl RedirectPolicy {
+    /// Creates a [`Custom`](#variant.Custom) policy from `policy`.
+    pub fn custom<F>(policy: F) -> RedirectPolicy
+    where
+        F: Fn(&Url, &[Url]) -> bool + Send + Sync + 'static,
+    {
+        RedirectPolicy::Custom(Arc::new(policy))
+    }
+
+    /// Decides whether to follow the redirect to `next`, after having
+    /// been redirected from each of `previous`.
+    pub(crate) fn follow(&self, next: &Url, previous: &[Url]) -> Result<bool, Error> {
+        if previous.len() > MAX_REDIRECTS {
+            return Err(Error::TooManyRedirects(MAX_REDIRECTS));
+        }
+        match *self {
+            RedirectPolicy::None => Ok(false),
+            RedirectPolicy::Limited(max) if previous.len() > max => {
+                Err(Error::TooManyRedirects(max))
+            }
+            RedirectPolicy::Limited(_) => Ok(true),
+            RedirectPolicy::Custom(ref policy) => Ok(policy(next, previous)),
+        }
+    }
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> RedirectPolicy {
+        RedirectPolicy::Limited(10)
+    }
+}
+
+impl fmt::Debug for RedirectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RedirectPolicy::None => write!(f, "None"),
+            RedirectPolicy::Limited(max) => write!(f, "Limited({})", max),
+            RedirectPolicy::Custom(_) => write!(f, "Custom(<closure>)"),
+        }
+    }
+}
+
+/// A redirect that was followed while sending a request, see
+/// [`Response::history`](struct.Response.html#method.history).
+#[derive(Clone, Debug)]
+pub struct Redirect {
+    url: Url,
+    status: Status,
+    location: String,
+}
+
+impl Redirect {
+    pub(crate) fn new(url: Url, status: Status, location: String) -> Redirect {
+        Redirect {
+            url,
+            status,
+            location,
+        }
+    }
+
+    /// Returns the URL that responded with the redirect.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns the status code of the redirect, eg. 301.
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+
+    /// Returns the `Location` header of the redirect, as sent by the
+    /// server. It may be relative to [`url`](#method.url).
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+}
+
+/// Checks whether `status` is one of the redirect status codes.
+pub(crate) fn is_redirect(status: i32) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
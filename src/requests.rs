@@ -1,7 +1,8 @@
-use crate::http::{Method, Request, URL};
+use crate::client::Client;
+use crate::http::{Method, Request};
 
-/// Sends a request to `url` with `method`, returns the response or
-/// an [`Error`](https://doc.rust-lang.org/std/io/struct.Error.html).
+/// Creates a request to `url` with `method`, which can then be sent
+/// for the response or an [`Error`](enum.Error.html).
 ///
 /// In most cases it is recommended to use one of the aliases of this
 /// function: [`get`](fn.get.html), [`head`](fn.head.html),
@@ -11,60 +12,64 @@ use crate::http::{Method, Request, URL};
 /// [`patch`](fn.patch.html). They omit the `method` parameter, since
 /// it is implied in the name, and the body is as optional as it is on
 /// [Wikipedia](https://en.wikipedia.org/wiki/Hypertext_Transfer_Protocol#Summary_table).
-pub fn create_request<T: Into<URL>>(method: Method, url: T) -> Request {
-    Request::new(method, url.into())
+///
+/// This uses a new [`Client`](struct.Client.html) with the default
+/// settings, so no connections are reused between requests made
+/// with these functions. Use a `Client` of your own for that.
+pub fn create_request<T: Into<String>>(method: Method, url: T) -> Request {
+    Client::new().request(method, url)
 }
 
 /// Alias for [send](fn.send.html) with `method` set to
 /// [Method::Get](enum.Method.html).
-pub fn get<T: Into<URL>>(url: T) -> Request {
+pub fn get<T: Into<String>>(url: T) -> Request {
     create_request(Method::Get, url)
 }
 
 /// Alias for [send](fn.send.html) with `method` set to
 /// [Method::Head](enum.Method.html).
-pub fn head<T: Into<URL>>(url: T) -> Request {
+pub fn head<T: Into<String>>(url: T) -> Request {
     create_request(Method::Head, url)
 }
 
 /// Alias for [send](fn.send.html) with `method` set to
 /// [Method::Post](enum.Method.html).
-pub fn post<T: Into<URL>>(url: T) -> Request {
+pub fn post<T: Into<String>>(url: T) -> Request {
     create_request(Method::Post, url)
 }
 
 /// Alias for [send](fn.send.html) with `method` set to
 /// [Method::Put](enum.Method.html).
-pub fn put<T: Into<URL>>(url: T) -> Request {
+pub fn put<T: Into<String>>(url: T) -> Request {
     create_request(Method::Put, url)
 }
 
 /// Alias for [send](fn.send.html) with `method` set to
 /// [Method::Delete](enum.Method.html).
-pub fn delete<T: Into<URL>>(url: T) -> Request {
+pub fn delete<T: Into<String>>(url: T) -> Request {
     create_request(Method::Delete, url)
 }
 
 /// Alias for [send](fn.send.html) with `method` set to
 /// [Method::Connect](enum.Method.html).
-pub fn connect<T: Into<URL>>(url: T) -> Request {
+pub fn connect<T: Into<String>>(url: T) -> Request {
     create_request(Method::Connect, url)
 }
 
 /// Alias for [send](fn.send.html) with `method` set to
 /// [Method::Options](enum.Method.html).
-pub fn options<T: Into<URL>>(url: T) -> Request {
+pub fn options<T: Into<String>>(url: T) -> Request {
     create_request(Method::Options, url)
 }
 
 /// Alias for [send](fn.send.html) with `method` set to
 /// [Method::Trace](enum.Method.html).
-pub fn trace<T: Into<URL>>(url: T) -> Request {
+pub fn trace<T: Into<String>>(url: T) -> Request {
     create_request(Method::Trace, url)
 }
 
 /// Alias for [send](fn.send.html) with `method` set to
 /// [Method::Patch](enum.Method.html).
-pub fn patch<T: Into<URL>>(url: T) -> Request {
+pub fn patch<T: Into<String>>(url: T) -> Request {
     create_request(Method::Patch, url)
 }
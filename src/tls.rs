@@ -0,0 +1,837 @@
+use crate::der::{signed_data, subject_alt_ip_addresses, subject_public_key_info};
+use crate::encoding::base64_encode;
+use crate::error::Error;
+use crate::hash::sha256;
+use rustls::internal::msgs::codec::Codec;
+use rustls::internal::msgs::persist::ClientSessionKey;
+use rustls::internal::pemfile;
+use rustls::{
+    sign, Certificate, ClientConfig, ClientSession, NoClientSessionStorage, PrivateKey,
+    ProtocolVersion, RootCertStore, ServerCertVerified, ServerCertVerifier, Session,
+    StoresClientSessions, SupportedCipherSuite, TLSError, ALL_CIPHERSUITES,
+};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use untrusted::Input;
+use webpki::{DNSNameRef, EndEntityCert, SignatureAlgorithm, TLSServerTrustAnchors, TrustAnchor};
+use webpki_roots::TLS_SERVER_ROOTS;
+
+/// Where the root certificates of the operating system are usually
+/// found, as a single PEM bundle.
+const NATIVE_BUNDLES: &[&str] = &[
+    // Debian, Ubuntu, Arch, Gentoo
+    "/etc/ssl/certs/ca-certificates.crt",
+    // Fedora, RHEL, CentOS
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    // openSUSE
+    "/etc/ssl/ca-bundle.pem",
+    // Alpine, macOS, the BSDs
+    "/etc/ssl/cert.pem",
+];
+
+/// The signature algorithms accepted in certificates, the same as
+/// rustls accepts.
+static SIGNATURE_ALGORITHMS: &[&SignatureAlgorithm] = &[
+    &webpki::ECDSA_P256_SHA256,
+    &webpki::ECDSA_P256_SHA384,
+    &webpki::ECDSA_P384_SHA256,
+    &webpki::ECDSA_P384_SHA384,
+    &webpki::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
+    &webpki::RSA_PSS_2048_8192_SHA384_LEGACY_KEY,
+    &webpki::RSA_PSS_2048_8192_SHA512_LEGACY_KEY,
+    &webpki::RSA_PKCS1_2048_8192_SHA256,
+    &webpki::RSA_PKCS1_2048_8192_SHA384,
+    &webpki::RSA_PKCS1_2048_8192_SHA512,
+    &webpki::RSA_PKCS1_3072_8192_SHA384,
+];
+
+/// The TLS protocol versions, see
+/// [`TlsConfig::with_min_protocol_version`](struct.TlsConfig.html#method.with_min_protocol_version).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    /// TLS 1.2.
+    Tls12,
+    /// TLS 1.3.
+    Tls13,
+}
+
+/// The TLS settings of HTTPS requests, set with
+/// [`Request::with_tls_config`](struct.Request.html#method.with_tls_config)
+/// or [`Client::with_tls_config`](struct.Client.html#method.with_tls_config).
+///
+/// Servers are always verified against the
+/// [Mozilla root certificates](https://github.com/ctz/webpki-roots),
+/// and also against the root certificates added here.
+///
+/// The rustls configuration is only built once, when the first
+/// connection is made, and is shared with the clones of the
+/// `TlsConfig`. It keeps up to 32 TLS sessions, so that new
+/// connections to the same servers can resume them instead of going
+/// through a full handshake. Requests without a `TlsConfig`
+/// share a default one.
+///
+/// # Example
+/// ```no_run
+/// # fn main() -> Result<(), mrq::Error> {
+/// let tls = mrq::TlsConfig::new()
+///     .with_root_certificates_pem(&std::fs::read("internal-ca.pem")?)?
+///     .with_client_certificate(
+///         &std::fs::read("client.pem")?,
+///         &std::fs::read("client.key")?,
+///     )?;
+/// let response = mrq::get("https://internal.example.com")
+///     .with_tls_config(tls)
+///     .send()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct TlsConfig {
+    roots: Vec<Certificate>,
+    client_certificate: Option<(Vec<Certificate>, PrivateKey)>,
+    accept_invalid_certs: bool,
+    accepted_fingerprints: Vec<[u8; 32]>,
+    pinned_keys: Vec<String>,
+    min_protocol_version: Option<TlsVersion>,
+    cipher_suites: Vec<&'static SupportedCipherSuite>,
+    disable_sni: bool,
+    server_name: Option<String>,
+    client_config: SharedConfig,
+}
+
+/// The rustls configuration built from a `TlsConfig`, shared by its
+/// clones until they are changed.
+#[derive(Clone, Default)]
+struct SharedConfig(Arc<Shared>);
+
+#[derive(Default)]
+struct Shared {
+    client_config: OnceLock<Arc<ClientConfig>>,
+    ip_client_config: OnceLock<Arc<ClientConfig>>,
+    sessions: Arc<SessionCache>,
+}
+
+/// The TLS sessions kept for resumption, up to 32 like rustls does.
+/// Each session is kept with the certificates the server presented in
+/// the full handshake it came from, as the server doesn't present
+/// them again when resuming it, so that both are dropped together.
+#[derive(Default)]
+struct SessionCache(Mutex<HashMap<Vec<u8>, CachedSession>>);
+
+#[derive(Default)]
+struct CachedSession {
+    value: Option<Vec<u8>>,
+    certificates: Vec<Certificate>,
+}
+
+impl SessionCache {
+    /// Updates the entry for `key` with `f`, making room for it first
+    /// if it is new. Returns false if the cache can't be used.
+    fn update<F: FnOnce(&mut CachedSession)>(&self, key: Vec<u8>, f: F) -> bool {
+        let mut sessions = match self.0.lock() {
+            Ok(sessions) => sessions,
+            Err(_) => return false,
+        };
+        if sessions.len() >= 32 && !sessions.contains_key(&key) {
+            if let Some(evicted) = sessions.keys().next().cloned() {
+                sessions.remove(&evicted);
+            }
+        }
+        f(sessions.entry(key).or_default());
+        true
+    }
+
+    fn certificates(&self, key: &[u8]) -> Vec<Certificate> {
+        match self.0.lock() {
+            Ok(sessions) => sessions
+                .get(key)
+                .map(|session| session.certificates.clone())
+                .unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+impl StoresClientSessions for SessionCache {
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> bool {
+        self.update(key, |session| session.value = Some(value))
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.0.lock().ok()?.get(key)?.value.clone()
+    }
+}
+
+impl TlsConfig {
+    /// Creates a `TlsConfig` that only trusts the Mozilla root
+    /// certificates, without a client certificate.
+    pub fn new() -> TlsConfig {
+        TlsConfig::default()
+    }
+
+    /// Adds a DER-encoded root certificate to trust. Fails with
+    /// [`Error::TlsError`](enum.Error.html) if the certificate can't
+    /// be parsed.
+    pub fn with_root_certificate(mut self, der: &[u8]) -> Result<TlsConfig, Error> {
+        let certificate = Certificate(der.to_vec());
+        RootCertStore::empty()
+            .add(&certificate)
+            .map_err(|err| tls_error(&format!("invalid root certificate: {:?}", err)))?;
+        self.roots.push(certificate);
+        self.client_config = SharedConfig::default();
+        Ok(self)
+    }
+
+    /// Adds every certificate of a PEM bundle as a root certificate
+    /// to trust. Fails with [`Error::TlsError`](enum.Error.html) if
+    /// there are none, or if one can't be parsed.
+    pub fn with_root_certificates_pem(mut self, pem: &[u8]) -> Result<TlsConfig, Error> {
+        let certificates = read_certificates(pem)?;
+        for certificate in certificates {
+            self = self.with_root_certificate(&certificate.0)?;
+        }
+        Ok(self)
+    }
+
+    /// Adds the root certificates trusted by the operating system.
+    /// They are read from the PEM bundle named by the `SSL_CERT_FILE`
+    /// environment variable if it is set, and otherwise from where
+    /// the common Linux distributions, macOS and the BSDs keep it.
+    /// Certificates the bundle contains that can't be parsed are
+    /// skipped. Fails with [`Error::TlsError`](enum.Error.html) if
+    /// no bundle is found.
+    pub fn with_native_roots(self) -> Result<TlsConfig, Error> {
+        let path = match env::var("SSL_CERT_FILE") {
+            Ok(path) => path,
+            Err(_) => NATIVE_BUNDLES
+                .iter()
+                .find(|path| Path::new(path).is_file())
+                .map(|path| path.to_string())
+                .ok_or_else(|| tls_error("no root certificate bundle found"))?,
+        };
+        self.with_native_roots_from(&path)
+    }
+
+    /// Adds the root certificates of the PEM bundle at `path`, as
+    /// [`with_native_roots`](#method.with_native_roots) does once it
+    /// has found the bundle.
+    pub(crate) fn with_native_roots_from(mut self, path: &str) -> Result<TlsConfig, Error> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let certificates = pemfile::certs(&mut reader)
+            .map_err(|_| tls_error(&format!("invalid root certificates in {}", path)))?;
+        let mut store = RootCertStore::empty();
+        for certificate in certificates {
+            if store.add(&certificate).is_ok() {
+                self.roots.push(certificate);
+            }
+        }
+        if store.is_empty() {
+            return Err(tls_error(&format!("no root certificates in {}", path)));
+        }
+        self.client_config = SharedConfig::default();
+        Ok(self)
+    }
+
+    /// Sets the client certificate to authenticate with, for servers
+    /// that ask for one. `chain` is the PEM-encoded certificate,
+    /// followed by any intermediate certificates, and `key` is its
+    /// PEM-encoded PKCS#8 or RSA private key. Fails with
+    /// [`Error::TlsError`](enum.Error.html) if either can't be
+    /// parsed, or if the key isn't RSA or ECDSA.
+    pub fn with_client_certificate(mut self, chain: &[u8], key: &[u8]) -> Result<TlsConfig, Error> {
+        let chain = read_certificates(chain)?;
+        let key = pemfile::pkcs8_private_keys(&mut &key[..])
+            .ok()
+            .and_then(|mut keys| keys.pop())
+            .or_else(|| {
+                pemfile::rsa_private_keys(&mut &key[..])
+                    .ok()
+                    .and_then(|mut keys| keys.pop())
+            })
+            .ok_or_else(|| tls_error("no private key found"))?;
+        sign::any_supported_type(&key).map_err(|_| tls_error("unsupported private key"))?;
+        self.client_certificate = Some((chain, key));
+        self.client_config = SharedConfig::default();
+        Ok(self)
+    }
+
+    /// Accepts any certificate the server presents, without checking
+    /// who signed it, whether it has expired, or whether it is for
+    /// the right host.
+    ///
+    /// **This makes HTTPS insecure**: anyone between the client and
+    /// the server can read and change the requests and responses. It
+    /// is only meant for testing against servers with self-signed
+    /// certificates.
+    pub fn danger_accept_invalid_certs(mut self) -> TlsConfig {
+        self.accept_invalid_certs = true;
+        self.client_config = SharedConfig::default();
+        self
+    }
+
+    /// Accepts the certificate with the SHA-256 `fingerprint`, given
+    /// in hexadecimal, optionally separated by colons like `openssl
+    /// x509 -fingerprint -sha256` prints it. The certificate is
+    /// accepted without any other checks, while other certificates
+    /// are still verified as usual. Fails with
+    /// [`Error::TlsError`](enum.Error.html) if the fingerprint isn't
+    /// 32 bytes of hexadecimal.
+    ///
+    /// **This is insecure** unless the fingerprint is known to
+    /// belong to the server, and its key hasn't leaked. It is meant
+    /// for testing against servers with self-signed certificates.
+    pub fn danger_accept_fingerprint(mut self, fingerprint: &str) -> Result<TlsConfig, Error> {
+        let fingerprint = parse_fingerprint(fingerprint)
+            .ok_or_else(|| tls_error("invalid SHA-256 fingerprint"))?;
+        self.accepted_fingerprints.push(fingerprint);
+        self.client_config = SharedConfig::default();
+        Ok(self)
+    }
+
+    /// Pins a public key: the certificates the server presents have
+    /// to include one with a pinned public key, on top of being
+    /// verified as usual. Otherwise, the request fails with
+    /// [`Error::PinnedKeyMismatch`](enum.Error.html) right after the
+    /// TLS handshake, before anything is sent. TLS sessions aren't
+    /// resumed when keys are pinned, since the server doesn't present
+    /// its certificates again when resuming one.
+    ///
+    /// `pin` is the base64-encoded SHA-256 digest of the DER-encoded
+    /// `SubjectPublicKeyInfo` of the key, optionally prefixed with
+    /// `sha256/`, as used by HTTP Public Key Pinning. It can be
+    /// computed from a certificate with:
+    /// ```sh
+    /// openssl x509 -in cert.pem -pubkey -noout | openssl pkey -pubin -outform der \
+    ///     | openssl dgst -sha256 -binary | base64
+    /// ```
+    /// Fails with [`Error::TlsError`](enum.Error.html) if the pin
+    /// isn't 32 bytes of base64.
+    pub fn with_pinned_public_key(mut self, pin: &str) -> Result<TlsConfig, Error> {
+        let pin = pin.trim();
+        let pin = pin.strip_prefix("sha256/").unwrap_or(pin);
+        let is_base64 = |c: char| c.is_ascii_alphanumeric() || c == '+' || c == '/';
+        if pin.len() != 44 || !pin.ends_with('=') || !pin[..43].chars().all(is_base64) {
+            return Err(tls_error("invalid public key pin"));
+        }
+        self.pinned_keys.push(pin.to_string());
+        self.client_config = SharedConfig::default();
+        Ok(self)
+    }
+
+    /// Sets the oldest TLS version to negotiate. By default, both TLS
+    /// 1.2 and TLS 1.3 are allowed, which are all the versions rustls
+    /// supports.
+    pub fn with_min_protocol_version(mut self, version: TlsVersion) -> TlsConfig {
+        self.min_protocol_version = Some(version);
+        self.client_config = SharedConfig::default();
+        self
+    }
+
+    /// Sets the cipher suites to offer, in order of preference, by
+    /// their IANA names, eg. `TLS13_AES_256_GCM_SHA384` or
+    /// `TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256`. The TLS 1.2 suites
+    /// are only used with TLS 1.2, and the TLS 1.3 suites with TLS
+    /// 1.3. Fails with [`Error::TlsError`](enum.Error.html) if
+    /// `names` is empty, or if rustls doesn't support one of them.
+    pub fn with_cipher_suites(mut self, names: &[&str]) -> Result<TlsConfig, Error> {
+        if names.is_empty() {
+            return Err(tls_error("no cipher suites given"));
+        }
+        let mut cipher_suites = Vec::new();
+        for name in names {
+            let suite = ALL_CIPHERSUITES
+                .iter()
+                .find(|suite| format!("{:?}", suite.suite).eq_ignore_ascii_case(name.trim()))
+                .ok_or_else(|| tls_error(&format!("unsupported cipher suite: {}", name)))?;
+            cipher_suites.push(*suite);
+        }
+        self.cipher_suites = cipher_suites;
+        self.client_config = SharedConfig::default();
+        Ok(self)
+    }
+
+    /// Sets whether the name of the server is sent in the TLS
+    /// handshake, with the Server Name Indication extension. It is
+    /// by default, except for IP addresses. Servers hosting several
+    /// sites might not know which certificate to present without it.
+    pub fn with_sni(mut self, enabled: bool) -> TlsConfig {
+        self.disable_sni = !enabled;
+        self.client_config = SharedConfig::default();
+        self
+    }
+
+    /// Sets the name of the server to send in the TLS handshake, and
+    /// to verify its certificate for, instead of the host of the
+    /// URL. This allows connecting to a server by its IP address, or
+    /// through another name, while still verifying its certificate.
+    /// Fails with [`Error::TlsError`](enum.Error.html) if `name` is
+    /// neither a valid DNS name nor an IP address.
+    pub fn with_server_name(mut self, name: &str) -> Result<TlsConfig, Error> {
+        if name.parse::<IpAddr>().is_err() && DNSNameRef::try_from_ascii_str(name).is_err() {
+            return Err(tls_error(&format!("invalid server name: {}", name)));
+        }
+        self.server_name = Some(name.to_string());
+        self.client_config = SharedConfig::default();
+        Ok(self)
+    }
+
+    /// Returns true if both configurations are clones that haven't
+    /// been changed since, and so share their rustls configuration.
+    pub(crate) fn shares_config(&self, other: &TlsConfig) -> bool {
+        Arc::ptr_eq(&self.client_config.0, &other.client_config.0)
+    }
+
+    /// Returns the address of the shared rustls configuration, which
+    /// identifies it as long as this `TlsConfig` is alive.
+    pub(crate) fn config_id(&self) -> usize {
+        Arc::as_ptr(&self.client_config.0) as usize
+    }
+
+    /// Returns true if public keys are pinned, and the certificates
+    /// have to be checked with [`check_pins`](#method.check_pins).
+    pub(crate) fn has_pins(&self) -> bool {
+        !self.pinned_keys.is_empty()
+    }
+
+    /// Checks that one of `certificates` has a pinned public key. Only
+    /// the server's own certificate, which was verified, and the
+    /// intermediates that each signed the certificate before them are
+    /// considered, since the server could add any other certificate.
+    pub(crate) fn check_pins(&self, certificates: &[Certificate], host: &str) -> Result<(), Error> {
+        let mut previous: Option<&Certificate> = None;
+        for certificate in certificates {
+            if let Some(previous) = previous {
+                if !signed_by(previous, certificate) {
+                    break;
+                }
+            }
+            let pinned = subject_public_key_info(&certificate.0)
+                .is_some_and(|spki| self.pinned_keys.contains(&base64_encode(&sha256(spki))));
+            if pinned {
+                return Ok(());
+            }
+            previous = Some(certificate);
+        }
+        Err(Error::PinnedKeyMismatch(host.to_string()))
+    }
+
+    /// Returns the certificates the server presented in `session`,
+    /// whose handshake is done. Resumed sessions get those of the full
+    /// handshake they came from, as the server doesn't present them
+    /// again.
+    pub(crate) fn peer_certificates(
+        &self,
+        session: &ClientSession,
+        host: &str,
+    ) -> Vec<Certificate> {
+        let presented = session
+            .get_peer_certificates()
+            .filter(|certificates| !certificates.is_empty());
+        let name = self.session_name(host);
+        let key = match DNSNameRef::try_from_ascii_str(&name) {
+            // Rustls keeps the session under the same key.
+            Ok(dns_name) => ClientSessionKey::session_for_dns_name(dns_name).get_encoding(),
+            Err(_) => return presented.unwrap_or_default(),
+        };
+        let sessions = &self.client_config.0.sessions;
+        match presented {
+            Some(certificates) => {
+                let stored = certificates.clone();
+                sessions.update(key, |session| session.certificates = stored);
+                certificates
+            }
+            None => sessions.certificates(&key),
+        }
+    }
+
+    /// Starts a TLS session with `host`, or with the server name set
+    /// with [`with_server_name`](#method.with_server_name).
+    pub(crate) fn client_session(&self, host: &str) -> Result<ClientSession, Error> {
+        let server_name = self.server_name.as_deref().unwrap_or(host);
+        let name = self.session_name(host);
+        let dns_name = DNSNameRef::try_from_ascii_str(&name)
+            .map_err(|_| tls_error(&format!("invalid server name: {}", server_name)))?;
+        let config = match server_name.parse::<IpAddr>() {
+            Ok(_) => self.ip_client_config(),
+            Err(_) => self.client_config(),
+        };
+        Ok(ClientSession::new(&config, dns_name))
+    }
+
+    /// Returns the DNS name sessions with `host` are started with.
+    /// Rustls only starts sessions with DNS names, so IP addresses get
+    /// a placeholder name that is never sent, and their certificates
+    /// are verified for the address by the `Verifier` instead.
+    fn session_name(&self, host: &str) -> String {
+        let server_name = self.server_name.as_deref().unwrap_or(host);
+        match server_name.parse::<IpAddr>() {
+            Ok(ip) => ip_session_name(ip),
+            Err(_) => server_name.to_string(),
+        }
+    }
+
+    /// Returns the `TlsConfig` of requests without one.
+    pub(crate) fn shared_default() -> &'static TlsConfig {
+        static DEFAULT: OnceLock<TlsConfig> = OnceLock::new();
+        DEFAULT.get_or_init(TlsConfig::new)
+    }
+
+    /// Returns the rustls configuration, building it on first use.
+    fn client_config(&self) -> Arc<ClientConfig> {
+        self.client_config
+            .0
+            .client_config
+            .get_or_init(|| self.build())
+            .clone()
+    }
+
+    /// Returns the rustls configuration for sessions with IP
+    /// addresses, building it on first use. It doesn't send SNI, and
+    /// verifies certificates for the address the placeholder name of
+    /// the session stands for.
+    fn ip_client_config(&self) -> Arc<ClientConfig> {
+        let build = || {
+            let mut config = ClientConfig::clone(&self.client_config());
+            config.enable_sni = false;
+            config
+                .dangerous()
+                .set_certificate_verifier(Arc::new(self.verifier(true)));
+            Arc::new(config)
+        };
+        self.client_config
+            .0
+            .ip_client_config
+            .get_or_init(build)
+            .clone()
+    }
+
+    fn build(&self) -> Arc<ClientConfig> {
+        let mut config = ClientConfig::new();
+        if self.has_pins() {
+            config.set_persistence(Arc::new(NoClientSessionStorage {}));
+            config.enable_tickets = false;
+        } else {
+            config.set_persistence(self.client_config.0.sessions.clone());
+        }
+        config.set_protocols(&[b"http/1.1".to_vec()]);
+        if self.min_protocol_version == Some(TlsVersion::Tls13) {
+            config.versions = vec![ProtocolVersion::TLSv1_3];
+        }
+        if !self.cipher_suites.is_empty() {
+            config.ciphersuites = self.cipher_suites.clone();
+        }
+        config.enable_sni = !self.disable_sni;
+        config
+            .root_store
+            .add_server_trust_anchors(&TLS_SERVER_ROOTS);
+        for root in &self.roots {
+            // The certificates were all checked when they were added.
+            config.root_store.add(root).ok();
+        }
+        if let Some((ref chain, ref key)) = self.client_certificate {
+            config.set_single_client_cert(chain.clone(), key.clone());
+        }
+        if self.accept_invalid_certs || !self.accepted_fingerprints.is_empty() {
+            config
+                .dangerous()
+                .set_certificate_verifier(Arc::new(self.verifier(false)));
+        }
+        Arc::new(config)
+    }
+
+    fn verifier(&self, ip_names: bool) -> Verifier {
+        Verifier {
+            accept_invalid_certs: self.accept_invalid_certs,
+            accepted_fingerprints: self.accepted_fingerprints.clone(),
+            ip_names,
+        }
+    }
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TlsConfig {{ roots: {}, client_certificate: {}, accept_invalid_certs: {}, accepted_fingerprints: {}, pinned_keys: {:?}, min_protocol_version: {:?}, cipher_suites: {:?}, sni: {}, server_name: {:?} }}",
+            self.roots.len(),
+            self.client_certificate.is_some(),
+            self.accept_invalid_certs,
+            self.accepted_fingerprints.len(),
+            self.pinned_keys,
+            self.min_protocol_version,
+            self.cipher_suites.iter().map(|suite| suite.suite).collect::<Vec<_>>(),
+            !self.disable_sni,
+            self.server_name
+        )
+    }
+}
+
+/// Details of the TLS session a response was received over, see
+/// [`Response::tls_info`](struct.Response.html#method.tls_info).
+#[derive(Clone, Debug)]
+pub struct TlsInfo {
+    protocol_version: String,
+    cipher_suite: String,
+    alpn_protocol: Option<Vec<u8>>,
+    peer_certificates: Vec<Vec<u8>>,
+}
+
+impl TlsInfo {
+    pub(crate) fn new(session: &ClientSession, peer_certificates: &[Certificate]) -> TlsInfo {
+        let protocol_version = match session.get_protocol_version() {
+            Some(ProtocolVersion::TLSv1_3) => "TLSv1.3".to_string(),
+            Some(ProtocolVersion::TLSv1_2) => "TLSv1.2".to_string(),
+            Some(version) => format!("{:?}", version),
+            None => String::new(),
+        };
+        let cipher_suite = match session.get_negotiated_ciphersuite() {
+            Some(suite) => format!("{:?}", suite.suite),
+            None => String::new(),
+        };
+        TlsInfo {
+            protocol_version,
+            cipher_suite,
+            alpn_protocol: session.get_alpn_protocol().map(|p| p.to_vec()),
+            peer_certificates: peer_certificates
+                .iter()
+                .map(|certificate| certificate.0.clone())
+                .collect(),
+        }
+    }
+
+    /// Returns the negotiated protocol version, `TLSv1.2` or
+    /// `TLSv1.3`.
+    pub fn protocol_version(&self) -> &str {
+        &self.protocol_version
+    }
+
+    /// Returns the IANA name of the negotiated cipher suite, eg.
+    /// `TLS13_AES_256_GCM_SHA384` or
+    /// `TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256`.
+    pub fn cipher_suite(&self) -> &str {
+        &self.cipher_suite
+    }
+
+    /// Returns the protocol the server selected with ALPN, which is
+    /// `http/1.1` if it supports ALPN.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+
+    /// Returns the DER-encoded certificates the server presented,
+    /// starting with its own. If the TLS session was resumed, these
+    /// are the certificates of the full handshake it was resumed
+    /// from, as the server doesn't present them again.
+    pub fn peer_certificates(&self) -> &[Vec<u8>] {
+        &self.peer_certificates
+    }
+}
+
+/// Verifies the certificates of servers, like rustls does by default
+/// but with the exceptions a [`TlsConfig`](struct.TlsConfig.html)
+/// allows. If `ip_names` is set, the DNS names of sessions are the
+/// placeholders of IP addresses, and certificates are verified for
+/// the address instead.
+struct Verifier {
+    accept_invalid_certs: bool,
+    accepted_fingerprints: Vec<[u8; 32]>,
+    ip_names: bool,
+}
+
+impl ServerCertVerifier for Verifier {
+    fn verify_server_cert(
+        &self,
+        roots: &RootCertStore,
+        presented_certs: &[Certificate],
+        dns_name: DNSNameRef,
+        _ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        if self.accept_invalid_certs {
+            return Ok(ServerCertVerified::assertion());
+        }
+        if let Some(certificate) = presented_certs.first() {
+            if self.accepted_fingerprints.contains(&sha256(&certificate.0)) {
+                return Ok(ServerCertVerified::assertion());
+            }
+        }
+        let ip = if self.ip_names {
+            let ip = parse_ip_session_name(dns_name.into());
+            Some(ip.ok_or(TLSError::WebPKIError(webpki::Error::CertNotValidForName))?)
+        } else {
+            None
+        };
+        verify_chain(roots, presented_certs, dns_name, ip)?;
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Checks that the first of `presented_certs` is valid for `ip` if it
+/// is set, or else for `dns_name`, and is signed by one of `roots`
+/// through the others.
+fn verify_chain(
+    roots: &RootCertStore,
+    presented_certs: &[Certificate],
+    dns_name: DNSNameRef,
+    ip: Option<IpAddr>,
+) -> Result<(), TLSError> {
+    let (certificate, intermediates) = match presented_certs.split_first() {
+        Some((certificate, intermediates)) => (certificate, intermediates),
+        None => return Err(TLSError::NoCertificatesPresented),
+    };
+    let certificate =
+        EndEntityCert::from(Input::from(&certificate.0)).map_err(TLSError::WebPKIError)?;
+    let intermediates: Vec<Input> = intermediates.iter().map(|c| Input::from(&c.0)).collect();
+    let anchors: Vec<TrustAnchor> = roots.roots.iter().map(|r| r.to_trust_anchor()).collect();
+    let now =
+        webpki::Time::try_from(SystemTime::now()).map_err(|_| TLSError::FailedToGetCurrentTime)?;
+    certificate
+        .verify_is_valid_tls_server_cert(
+            SIGNATURE_ALGORITHMS,
+            &TLSServerTrustAnchors(&anchors),
+            &intermediates,
+            now,
+        )
+        .map_err(TLSError::WebPKIError)?;
+    match ip {
+        Some(ip) => {
+            // Webpki only checks DNS names, so IP addresses are
+            // compared with the certificate's IP SANs here.
+            let octets = ip_octets(ip);
+            if subject_alt_ip_addresses(&presented_certs[0].0).contains(&&octets[..]) {
+                Ok(())
+            } else {
+                Err(TLSError::WebPKIError(webpki::Error::CertNotValidForName))
+            }
+        }
+        None => certificate
+            .verify_is_valid_for_dns_name(dns_name)
+            .map_err(TLSError::WebPKIError),
+    }
+}
+
+/// Returns true if `certificate` is signed with the key of `issuer`.
+fn signed_by(certificate: &Certificate, issuer: &Certificate) -> bool {
+    let (tbs, signature) = match signed_data(&certificate.0) {
+        Some(signed) => signed,
+        None => return false,
+    };
+    // Webpki only verifies signatures with the key of an end-entity
+    // certificate, but parsing the issuer as one doesn't check that
+    // it isn't a CA.
+    let issuer = match EndEntityCert::from(Input::from(&issuer.0)) {
+        Ok(issuer) => issuer,
+        Err(_) => return false,
+    };
+    SIGNATURE_ALGORITHMS.iter().any(|algorithm| {
+        issuer
+            .verify_signature(algorithm, Input::from(tbs), Input::from(signature))
+            .is_ok()
+    })
+}
+
+/// Reads the certificates of a PEM bundle, failing if there are none.
+fn read_certificates(pem: &[u8]) -> Result<Vec<Certificate>, Error> {
+    match pemfile::certs(&mut &pem[..]) {
+        Ok(ref certificates) if certificates.is_empty() => Err(tls_error("no certificates found")),
+        Ok(certificates) => Ok(certificates),
+        Err(_) => Err(tls_error("invalid PEM certificates")),
+    }
+}
+
+/// Parses a SHA-256 fingerprint in hexadecimal, ignoring colons.
+fn parse_fingerprint(fingerprint: &str) -> Option<[u8; 32]> {
+    let digits: Vec<u8> = fingerprint
+        .chars()
+        .filter(|&c| c != ':')
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()?;
+    if digits.len() != 64 {
+        return None;
+    }
+    let mut bytes = [0; 32];
+    for (byte, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+        *byte = (pair[0] << 4) | pair[1];
+    }
+    Some(bytes)
+}
+
+/// Returns the placeholder name sessions with `ip` are started with.
+/// Distinct names keep the sessions of each address apart.
+fn ip_session_name(ip: IpAddr) -> String {
+    let hex: String = ip_octets(ip).iter().map(|b| format!("{:02x}", b)).collect();
+    format!("ip-{}.invalid", hex)
+}
+
+/// Returns the IP address a name from
+/// [`ip_session_name`](fn.ip_session_name.html) stands for.
+fn parse_ip_session_name(name: &str) -> Option<IpAddr> {
+    let hex = name.strip_prefix("ip-")?.strip_suffix(".invalid")?;
+    let octets: Vec<u8> = hex
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect::<Option<_>>()?;
+    match octets.len() {
+        4 => Some(IpAddr::from([octets[0], octets[1], octets[2], octets[3]])),
+        16 => {
+            let mut v6 = [0; 16];
+            v6.copy_from_slice(&octets);
+            Some(IpAddr::from(v6))
+        }
+        _ => None,
+    }
+}
+
+fn ip_octets(ip: IpAddr) -> Vec<u8> {
+    match ip {
+        IpAddr::V4(ip) => ip.octets().to_vec(),
+        IpAddr::V6(ip) => ip.octets().to_vec(),
+    }
+}
+
+fn tls_error(message: &str) -> Error {
+    Error::TlsError(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn native_roots_from() {
+        let certs = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/certs");
+        let ca = format!("{}/ca.pem", certs);
+        let tls = TlsConfig::new().with_native_roots_from(&ca).unwrap();
+        let expected = read_certificates(&std::fs::read(&ca).unwrap()).unwrap();
+        assert_eq!(tls.roots, expected);
+        match TlsConfig::new().with_native_roots_from(&format!("{}/client.key", certs)) {
+            Err(Error::TlsError(message)) => assert!(message.starts_with("no root certificates")),
+            other => panic!("expected a TLS error, got {:?}", other.map(|_| ())),
+        }
+        assert!(TlsConfig::new()
+            .with_native_roots_from(&format!("{}/missing.pem", certs))
+            .is_err());
+    }
+
+    #[test]
+    fn ip_sessions() {
+        for ip in &["127.0.0.1", "::1"] {
+            let ip: IpAddr = ip.parse().unwrap();
+            assert_eq!(parse_ip_session_name(&ip_session_name(ip)), Some(ip));
+        }
+        assert_eq!(parse_ip_session_name("localhost"), None);
+        // The configuration for IP addresses is built once, and shared
+        // by the clones.
+        let tls = TlsConfig::new();
+        let config = tls.ip_client_config();
+        assert!(Arc::ptr_eq(&config, &tls.clone().ip_client_config()));
+        assert!(!config.enable_sni);
+    }
+}
@@ -0,0 +1,409 @@
+use crate::encoding::percent_encode_invalid;
+use std::error;
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::str::FromStr;
+
+/// A parsed `http` or `https` URL, as described in
+/// [RFC 3986](https://tools.ietf.org/html/rfc3986).
+///
+/// # Example
+/// ```
+/// let url = mrq::Url::parse("https://user:pass@[::1]:8080/a/b?c=d#e").unwrap();
+/// assert_eq!(url.scheme(), "https");
+/// assert_eq!(url.username(), Some("user"));
+/// assert_eq!(url.password(), Some("pass"));
+/// assert_eq!(url.host(), "::1");
+/// assert_eq!(url.port(), 8080);
+/// assert_eq!(url.path(), "/a/b");
+/// assert_eq!(url.query(), Some("c=d"));
+/// assert_eq!(url.fragment(), Some("e"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Url {
+    scheme: String,
+    username: Option<String>,
+    password: Option<String>,
+    host: String,
+    port: Option<u16>,
+    path: String,
+    query: Option<String>,
+    fragment: Option<String>,
+}
+
+/// The reasons a URL can fail to parse.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The URL doesn't start with a scheme followed by `://`.
+    MissingScheme,
+    /// The scheme is something other than `http` or `https`.
+    UnsupportedScheme(String),
+    /// The URL has no host.
+    EmptyHost,
+    /// The host contains characters that aren't allowed in it, or is
+    /// a malformed IPv6 literal.
+    InvalidHost(String),
+    /// The port is not a number between 0 and 65535.
+    InvalidPort(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::MissingScheme => write!(f, "the URL has no scheme"),
+            ParseError::UnsupportedScheme(ref scheme) => {
+                write!(f, "unsupported URL scheme: {:?}", scheme)
+            }
+            ParseError::EmptyHost => write!(f, "the URL has no host"),
+            ParseError::InvalidHost(ref host) => write!(f, "invalid host: {:?}", host),
+            ParseError::InvalidPort(ref port) => write!(f, "invalid port: {:?}", port),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+impl Url {
+    /// Parses an absolute `http` or `https` URL.
+    ///
+    /// The scheme and host are case-insensitive, and are lowercased.
+    /// Characters that can't appear in a URL, like spaces, are
+    /// percent-encoded in the path, query and fragment.
+    pub fn parse(url: &str) -> Result<Url, ParseError> {
+        let url = url.trim();
+        let (scheme, rest) = match url.find("://") {
+            Some(idx) if is_valid_scheme(&url[..idx]) => (&url[..idx], &url[idx + 3..]),
+            _ => return Err(ParseError::MissingScheme),
+        };
+        let scheme = scheme.to_ascii_lowercase();
+        if scheme != "http" && scheme != "https" {
+            return Err(ParseError::UnsupportedScheme(scheme));
+        }
+
+        let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let (authority, rest) = rest.split_at(authority_end);
+        let (username, password, host, port) = parse_authority(authority)?;
+
+        let mut url = Url {
+            scheme,
+            username,
+            password,
+            host,
+            port,
+            path: String::new(),
+            query: None,
+            fragment: None,
+        };
+        url.set_path_and_after(rest);
+        Ok(url)
+    }
+
+    /// Returns the scheme, either `http` or `https`.
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Returns the username from the userinfo, if there is one. It is
+    /// returned as it appears in the URL, ie. still percent-encoded.
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+
+    /// Returns the password from the userinfo, if there is one. It is
+    /// returned as it appears in the URL, ie. still percent-encoded.
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    /// Returns the host. IPv6 addresses are returned without the
+    /// surrounding brackets.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Returns the port, which is the scheme's default port if the
+    /// URL doesn't specify one.
+    pub fn port(&self) -> u16 {
+        match self.port {
+            Some(port) => port,
+            None => self.default_port(),
+        }
+    }
+
+    /// Returns the path, which is always at least `/`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the query, without the leading `?`.
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// Returns the fragment, without the leading `#`. The fragment is
+    /// never sent to the server.
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_deref()
+    }
+
+    /// Resolves `reference` against this URL, as a browser would
+    /// resolve a link on the page at this URL.
+    pub fn join(&self, reference: &str) -> Result<Url, ParseError> {
+        let reference = reference.trim();
+        if let Some(idx) = reference.find(':') {
+            if is_valid_scheme(&reference[..idx]) {
+                return Url::parse(reference);
+            }
+        }
+        if reference.starts_with("//") {
+            return Url::parse(&format!("{}:{}", self.scheme, reference));
+        }
+
+        let mut url = self.clone();
+        url.fragment = None;
+        if reference.starts_with('/') {
+            url.set_path_and_after(reference);
+        } else if reference.starts_with('?') {
+            let path = url.path.clone();
+            url.set_path_and_after(&format!("{}{}", path, reference));
+        } else if reference.starts_with('#') || reference.is_empty() {
+            let resource = url.resource();
+            url.set_path_and_after(&format!("{}{}", resource, reference));
+        } else {
+            let directory = match url.path.rfind('/') {
+                Some(idx) => &url.path[..=idx],
+                None => "/",
+            };
+            let merged = format!("{}{}", directory, reference);
+            url.set_path_and_after(&merged);
+        }
+        Ok(url)
+    }
+
+    /// Returns the port, if the URL specifies one.
+    pub(crate) fn explicit_port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// Appends `pair` to the query, separated from any existing
+    /// parameters by a `&`.
+    pub(crate) fn append_query(&mut self, pair: &str) {
+        self.query = match self.query.take() {
+            Some(ref query) if !query.is_empty() && !query.ends_with('&') => {
+                Some(format!("{}&{}", query, pair))
+            }
+            Some(query) => Some(query + pair),
+            None => Some(pair.to_string()),
+        };
+    }
+
+    /// Returns this URL without the username and password, to be shown
+    /// without leaking them.
+    pub(crate) fn without_credentials(&self) -> Url {
+        Url {
+            username: None,
+            password: None,
+            ..self.clone()
+        }
+    }
+
+    /// Returns true if both URLs have the same scheme, host and port.
+    pub(crate) fn same_origin(&self, other: &Url) -> bool {
+        self.scheme == other.scheme && self.host == other.host && self.port() == other.port()
+    }
+
+    /// Returns true if the scheme is `https`.
+    pub(crate) fn is_https(&self) -> bool {
+        self.scheme == "https"
+    }
+
+    /// Returns the path and query, as they appear in the request line.
+    pub(crate) fn resource(&self) -> String {
+        match self.query {
+            Some(ref query) => format!("{}?{}", self.path, query),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Returns the value of the `Host` header for this URL: the host,
+    /// followed by the port if it isn't the default one.
+    pub(crate) fn host_header(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        match self.port {
+            Some(port) if port != self.default_port() => format!("{}:{}", host, port),
+            _ => host,
+        }
+    }
+
+    fn default_port(&self) -> u16 {
+        if self.is_https() {
+            443
+        } else {
+            80
+        }
+    }
+
+    /// Sets the path, query and fragment from the part of a URL
+    /// following the authority.
+    fn set_path_and_after(&mut self, s: &str) {
+        let (rest, fragment) = match s.find('#') {
+            Some(idx) => (&s[..idx], Some(percent_encode_invalid(&s[idx + 1..]))),
+            None => (s, None),
+        };
+        let (path, query) = match rest.find('?') {
+            Some(idx) => (&rest[..idx], Some(percent_encode_invalid(&rest[idx + 1..]))),
+            None => (rest, None),
+        };
+        self.path = remove_dot_segments(&percent_encode_invalid(path));
+        self.query = query;
+        self.fragment = fragment;
+    }
+}
+
+impl FromStr for Url {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Url, ParseError> {
+        Url::parse(s)
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}://", self.scheme)?;
+        if let Some(ref username) = self.username {
+            write!(f, "{}", username)?;
+            if let Some(ref password) = self.password {
+                write!(f, ":{}", password)?;
+            }
+            write!(f, "@")?;
+        }
+        if self.host.contains(':') {
+            write!(f, "[{}]", self.host)?;
+        } else {
+            write!(f, "{}", self.host)?;
+        }
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        write!(f, "{}", self.resource())?;
+        if let Some(ref fragment) = self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Url> for String {
+    fn from(url: Url) -> String {
+        url.to_string()
+    }
+}
+
+/// Checks that `scheme` is `ALPHA *( ALPHA / DIGIT / "+" / "-" / "." )`.
+fn is_valid_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+type Authority = (Option<String>, Option<String>, String, Option<u16>);
+
+/// Splits an authority into its username, password, host and port.
+fn parse_authority(authority: &str) -> Result<Authority, ParseError> {
+    let (userinfo, hostport) = match authority.rfind('@') {
+        Some(idx) => (Some(&authority[..idx]), &authority[idx + 1..]),
+        None => (None, authority),
+    };
+    let (username, password) = match userinfo {
+        Some(userinfo) => match userinfo.find(':') {
+            Some(idx) => (
+                Some(userinfo[..idx].to_string()),
+                Some(userinfo[idx + 1..].to_string()),
+            ),
+            None => (Some(userinfo.to_string()), None),
+        },
+        None => (None, None),
+    };
+
+    let bracketed = hostport.starts_with('[');
+    let (host, port) = if bracketed {
+        let end = match hostport.find(']') {
+            Some(end) => end,
+            None => return Err(ParseError::InvalidHost(hostport.to_string())),
+        };
+        let host = &hostport[1..end];
+        if host.parse::<Ipv6Addr>().is_err() {
+            return Err(ParseError::InvalidHost(host.to_string()));
+        }
+        let port = &hostport[end + 1..];
+        if !port.is_empty() && !port.starts_with(':') {
+            return Err(ParseError::InvalidHost(hostport.to_string()));
+        }
+        (host, port.get(1..))
+    } else {
+        match hostport.rfind(':') {
+            Some(idx) => (&hostport[..idx], Some(&hostport[idx + 1..])),
+            None => (hostport, None),
+        }
+    };
+
+    if host.is_empty() {
+        return Err(ParseError::EmptyHost);
+    }
+    // Colons are only allowed in IPv6 addresses, which are bracketed.
+    let is_valid_host_char = |c: char| {
+        c.is_ascii_alphanumeric()
+            || "-._~%!$&'()*+,;=".contains(c)
+            || (c == ':' && bracketed)
+            || !c.is_ascii()
+    };
+    if !host.chars().all(is_valid_host_char) {
+        return Err(ParseError::InvalidHost(host.to_string()));
+    }
+    let port = match port {
+        // An empty port is allowed, and means the default port.
+        Some("") | None => None,
+        Some(port) => match port.parse::<u16>() {
+            Ok(port) => Some(port),
+            Err(_) => return Err(ParseError::InvalidPort(port.to_string())),
+        },
+    };
+
+    Ok((username, password, host.to_ascii_lowercase(), port))
+}
+
+/// Resolves `.` and `..` segments in `path`, as described in
+/// [RFC 3986 section 5.2.4](https://tools.ietf.org/html/rfc3986#section-5.2.4).
+/// The returned path always starts with a `/`.
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut ends_with_slash = false;
+    for segment in path.split('/').skip_while(|s| s.is_empty()) {
+        ends_with_slash = true;
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => {
+                segments.push(segment);
+                ends_with_slash = false;
+            }
+        }
+    }
+    let mut path = String::from("/");
+    path += &segments.join("/");
+    if ends_with_slash && !segments.is_empty() {
+        path.push('/');
+    }
+    path
+}
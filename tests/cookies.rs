@@ -0,0 +1,123 @@
+extern crate mrq;
+
+use mrq::{CookieJar, SameSite, Url};
+use std::time::{Duration, UNIX_EPOCH};
+
+fn url(url: &str) -> Url {
+    Url::parse(url).unwrap()
+}
+
+#[test]
+fn test_attributes() {
+    let jar = CookieJar::new();
+    jar.insert(
+        &url("https://example.com/a/b"),
+        "id=1; Domain=.Example.com; Secure; HttpOnly; SameSite=lax; Max-Age=60",
+    );
+    let cookies = jar.cookies();
+    assert_eq!(cookies.len(), 1);
+    let cookie = &cookies[0];
+    assert_eq!((cookie.name(), cookie.value()), ("id", "1"));
+    assert_eq!(cookie.domain(), "example.com");
+    assert!(!cookie.host_only());
+    assert_eq!(cookie.path(), "/a");
+    assert!(cookie.secure() && cookie.http_only());
+    assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+    assert!(cookie.expires().is_some());
+}
+
+#[test]
+fn test_domain() {
+    let jar = CookieJar::new();
+    jar.insert(&url("http://example.com/"), "host=1");
+    jar.insert(
+        &url("http://www.example.com/"),
+        "parent=2; Domain=example.com",
+    );
+    jar.insert(&url("http://example.com/"), "other=3; Domain=example.org");
+    jar.insert(&url("http://example.com/"), "tld=4; Domain=com");
+    assert_eq!(
+        jar.cookie_header(&url("http://example.com/")),
+        Some("host=1; parent=2".to_string())
+    );
+    assert_eq!(
+        jar.cookie_header(&url("http://api.example.com/")),
+        Some("parent=2".to_string())
+    );
+    assert_eq!(jar.cookie_header(&url("http://example.org/")), None);
+}
+
+#[test]
+fn test_path() {
+    let jar = CookieJar::new();
+    jar.insert(&url("http://h/"), "root=1");
+    jar.insert(&url("http://h/"), "docs=2; Path=/docs");
+    assert_eq!(
+        jar.cookie_header(&url("http://h/docs/x")),
+        Some("docs=2; root=1".to_string())
+    );
+    assert_eq!(
+        jar.cookie_header(&url("http://h/docsx")),
+        Some("root=1".to_string())
+    );
+}
+
+#[test]
+fn test_secure() {
+    let jar = CookieJar::new();
+    jar.insert(&url("http://h/"), "insecure=1; Secure");
+    jar.insert(&url("https://h/"), "secure=2; Secure");
+    assert_eq!(jar.cookie_header(&url("http://h/")), None);
+    assert_eq!(
+        jar.cookie_header(&url("https://h/")),
+        Some("secure=2".to_string())
+    );
+}
+
+#[test]
+fn test_expiry() {
+    let jar = CookieJar::new();
+    jar.insert(&url("http://h/"), "a=1");
+    jar.insert(&url("http://h/"), "b=2");
+    jar.insert(&url("http://h/"), "a=3; Max-Age=0");
+    jar.insert(
+        &url("http://h/"),
+        "b=4; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+    );
+    jar.insert(
+        &url("http://h/"),
+        "c=5; Expires=Sun, 06 Nov 1994 08:49:37 GMT",
+    );
+    assert_eq!(jar.cookie_header(&url("http://h/")), None);
+
+    // Max-Age takes precedence over Expires.
+    jar.insert(
+        &url("http://h/"),
+        "d=6; Max-Age=60; Expires=Sun, 06 Nov 1994 08:49:37 GMT",
+    );
+    assert_eq!(
+        jar.cookie_header(&url("http://h/")),
+        Some("d=6".to_string())
+    );
+}
+
+#[test]
+fn test_expires_formats() {
+    let jar = CookieJar::new();
+    let expected = UNIX_EPOCH + Duration::from_secs(4_102_444_800);
+    for date in &[
+        "Fri, 01 Jan 2100 00:00:00 GMT",
+        "Friday, 01-Jan-2100 00:00:00 GMT",
+        "Fri Jan  1 00:00:00 2100",
+    ] {
+        jar.clear();
+        jar.insert(&url("http://h/"), &format!("a=1; Expires={}", date));
+        assert_eq!(jar.cookies()[0].expires(), Some(expected), "{}", date);
+    }
+    // Invalid dates are ignored, leaving a session cookie.
+    jar.insert(&url("http://h/"), "b=1; Expires=tomorrow");
+    assert!(jar
+        .cookies()
+        .iter()
+        .any(|c| c.name() == "b" && c.expires().is_none()));
+}
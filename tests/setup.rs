@@ -1,13 +1,32 @@
+extern crate md5;
 extern crate mrq;
+extern crate ring;
+#[cfg(feature = "https")]
+extern crate rustls;
 extern crate tiny_http;
+#[cfg(feature = "https")]
+use self::rustls::internal::pemfile;
+#[cfg(feature = "https")]
+use self::rustls::{
+    AllowAnyAuthenticatedClient, NoClientAuth, ProtocolVersion, RootCertStore, ServerConfig,
+    ServerSession, Session, StoresServerSessions, StreamOwned,
+};
+use self::tiny_http::{Header, Method, Response, Server};
+#[cfg(feature = "https")]
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, TcpListener, TcpStream};
+#[cfg(feature = "https")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+#[cfg(feature = "https")]
+use std::sync::Mutex;
+use std::sync::Once;
 use std::thread;
-use std::io::Error;
-use std::sync::{Once, ONCE_INIT};
 use std::time::Duration;
-use std::sync::Arc;
-use self::tiny_http::{Method, Response, Server};
 
-static INIT: Once = ONCE_INIT;
+static INIT: Once = Once::new();
 
 pub fn setup() {
     INIT.call_once(|| {
@@ -17,15 +36,17 @@ pub fn setup() {
 
             thread::spawn(move || loop {
                 let mut request = server.recv().unwrap();
-                let mut content = String::new();
-                request.as_reader().read_to_string(&mut content).ok();
+                let mut bytes = Vec::new();
+                request.as_reader().read_to_end(&mut bytes).ok();
+                let content = String::from_utf8_lossy(&bytes).into_owned();
                 let headers = Vec::from(request.headers());
 
                 let url = String::from(request.url());
+                #[allow(clippy::match_ref_pats)]
                 match request.method() {
                     &Method::Get if url == "/header_pong" => {
                         for header in headers {
-                            if header.field.as_str() == "Ping" {
+                            if header.field.equiv("Ping") {
                                 let response = Response::from_string(format!("{}", header.value));
                                 request.respond(response).ok();
                                 return;
@@ -38,6 +59,11 @@ pub fn setup() {
                         let response = Response::from_string(format!("j: {}", content));
                         request.respond(response).ok();
                     }
+                    &Method::Get if url == "/chunked" => {
+                        let response = Response::from_string(format!("s: {}", content))
+                            .with_chunked_threshold(0);
+                        request.respond(response).ok();
+                    }
                     &Method::Get if url == "/a" => {
                         let response = Response::from_string(format!("j: {}", content));
                         request.respond(response).ok();
@@ -49,6 +75,97 @@ pub fn setup() {
                         let response = Response::from_string(format!("l: {}", content));
                         request.respond(response).ok();
                     }
+                    &Method::Get if url == "/cookie_login" => {
+                        let response = Response::empty(302)
+                            .with_header(header("Set-Cookie", "session=abc; Path=/; HttpOnly"))
+                            .with_header(header("Set-Cookie", "theme=dark; Path=/settings"))
+                            .with_header(header("Location", "/cookie_echo"));
+                        request.respond(response).ok();
+                    }
+                    &Method::Get if url == "/cookie_echo" => {
+                        let cookies = headers
+                            .iter()
+                            .find(|header| header.field.equiv("Cookie"))
+                            .map(|header| header.value.to_string())
+                            .unwrap_or_else(|| "No cookies!".to_string());
+                        request.respond(Response::from_string(cookies)).ok();
+                    }
+                    &Method::Get if url == "/auth_echo" => {
+                        let authorization = headers
+                            .iter()
+                            .find(|header| header.field.equiv("Authorization"))
+                            .map(|header| header.value.to_string())
+                            .unwrap_or_else(|| "No auth!".to_string());
+                        request.respond(Response::from_string(authorization)).ok();
+                    }
+                    &Method::Get if url == "/auth_redirect" => {
+                        let response =
+                            Response::empty(302).with_header(header("Location", "/auth_echo"));
+                        request.respond(response).ok();
+                    }
+                    &Method::Get if url.starts_with("/digest") => {
+                        let authorization = headers
+                            .iter()
+                            .find(|header| header.field.equiv("Authorization"))
+                            .map(|header| header.value.to_string());
+                        let algorithm = digest_algorithm(&url);
+                        let response = match authorization {
+                            Some(ref authorization) if check_digest(authorization, &url) => {
+                                let nc = digest_param(authorization, "nc").unwrap();
+                                Response::from_string(format!("Welcome, {}", nc))
+                            }
+                            _ => Response::from_string("Who are you?")
+                                .with_status_code(401)
+                                .with_header(header(
+                                    "WWW-Authenticate",
+                                    "Digest realm=\"mrq\", qop=\"auth,auth-int\", nonce=\"abc\", opaque=\"xyz\"",
+                                ))
+                                .with_header(header(
+                                    "WWW-Authenticate",
+                                    &format!("Digest realm=\"mrq\", qop=\"auth\", algorithm={}, nonce=\"abc\", opaque=\"xyz\"", algorithm),
+                                )),
+                        };
+                        request.respond(response).ok();
+                    }
+                    &Method::Get if url == "/redirect_loop" => {
+                        let response = Response::empty(302)
+                            .with_header(header("Location", "/redirect_loop"));
+                        request.respond(response).ok();
+                    }
+                    &Method::Get if url.starts_with("/redirect/") => {
+                        let hops: usize = url["/redirect/".len()..].parse().unwrap();
+                        if hops == 0 {
+                            request.respond(Response::from_string("done")).ok();
+                        } else {
+                            let location = format!("/redirect/{}", hops - 1);
+                            let response =
+                                Response::empty(302).with_header(header("Location", &location));
+                            request.respond(response).ok();
+                        }
+                    }
+                    _ if url.starts_with("/status/") => {
+                        let status: u16 = url["/status/".len()..].parse().unwrap();
+                        let response = Response::empty(status)
+                            .with_header(header("Location", "/method_echo"));
+                        request.respond(response).ok();
+                    }
+                    _ if url == "/method_echo" => {
+                        let length = headers
+                            .iter()
+                            .find(|header| header.field.equiv("Content-Length"))
+                            .map(|header| header.value.to_string())
+                            .unwrap_or_else(|| "-".to_string());
+                        let method = request.method().as_str().to_string();
+                        let response =
+                            Response::from_string(format!("{} {} {}", method, length, content));
+                        request.respond(response).ok();
+                    }
+                    &Method::Get if url.starts_with("/query") => {
+                        request.respond(Response::from_string(url)).ok();
+                    }
+                    &Method::Post if url == "/echo" => {
+                        request.respond(Response::from_data(bytes)).ok();
+                    }
                     &Method::Put if url == "/d" => {
                         let response = Response::from_string(format!("m: {}", content));
                         request.respond(response).ok();
@@ -85,11 +202,444 @@ pub fn setup() {
     });
 }
 
+/// Serves `response` verbatim to the first connection made to the
+/// returned address, then closes the connection. Useful for responses
+/// tiny_http won't produce.
+pub fn serve_raw(response: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut head = Vec::new();
+        let mut byte = [0; 1];
+        while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap_or(0) == 1 {
+            head.push(byte[0]);
+        }
+        stream.write_all(response).ok();
+    });
+    format!("http://{}", addr)
+}
+
+/// Answers the first request made to the returned address with its
+/// own request line and headers, eg. to see what was sent to a proxy.
+pub fn serve_echo_head() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut head = Vec::new();
+        let mut byte = [0; 1];
+        while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap_or(0) == 1 {
+            head.push(byte[0]);
+        }
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            head.len()
+        );
+        stream.write_all(response.as_bytes()).ok();
+        stream.write_all(&head).ok();
+    });
+    format!("http://{}", addr)
+}
+
+/// Acts as a SOCKS5 proxy for the first connection made to the
+/// returned address, relaying it to the host the client asks for. If
+/// `credentials` are given, the client has to authenticate with them.
+/// The requested `host:port` is sent on the returned channel.
+pub fn serve_socks5(
+    credentials: Option<(&'static str, &'static str)>,
+) -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        let (mut client, _) = listener.accept().unwrap();
+        let read_vec = |client: &mut TcpStream, len: usize| {
+            let mut buf = vec![0; len];
+            client.read_exact(&mut buf).unwrap();
+            buf
+        };
+        let greeting = read_vec(&mut client, 2);
+        read_vec(&mut client, greeting[1] as usize);
+        match credentials {
+            Some((username, password)) => {
+                client.write_all(&[5, 2]).unwrap();
+                let len = read_vec(&mut client, 2)[1] as usize;
+                let user = read_vec(&mut client, len);
+                let len = read_vec(&mut client, 1)[0] as usize;
+                let pass = read_vec(&mut client, len);
+                let ok = user == username.as_bytes() && pass == password.as_bytes();
+                client.write_all(&[1, if ok { 0 } else { 1 }]).unwrap();
+                if !ok {
+                    return;
+                }
+            }
+            None => client.write_all(&[5, 0]).unwrap(),
+        }
+
+        let request = read_vec(&mut client, 4);
+        let host = match request[3] {
+            1 => {
+                let ip = read_vec(&mut client, 4);
+                Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]).to_string()
+            }
+            3 => {
+                let len = read_vec(&mut client, 1)[0] as usize;
+                String::from_utf8(read_vec(&mut client, len)).unwrap()
+            }
+            _ => {
+                let mut ip = [0; 16];
+                ip.copy_from_slice(&read_vec(&mut client, 16));
+                Ipv6Addr::from(ip).to_string()
+            }
+        };
+        let port = read_vec(&mut client, 2);
+        let port = u16::from(port[0]) << 8 | u16::from(port[1]);
+        sender.send(format!("{}:{}", host, port)).unwrap();
+
+        let mut server = TcpStream::connect((host.as_str(), port)).unwrap();
+        client.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0]).unwrap();
+        let mut client_reader = client.try_clone().unwrap();
+        let mut server_writer = server.try_clone().unwrap();
+        thread::spawn(move || io::copy(&mut client_reader, &mut server_writer));
+        io::copy(&mut server, &mut client).ok();
+    });
+    (addr.to_string(), receiver)
+}
+
+/// Answers every request with `<connection>:<request>`, counting
+/// connections and requests per connection from zero. Connections are
+/// closed after `requests` requests, and if `close` is set, the last
+/// response on a connection says so with `Connection: close`.
+pub fn serve_keep_alive(requests: usize, close: bool) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for (conn, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.unwrap();
+            thread::spawn(move || {
+                for req in 0..requests {
+                    let mut head = Vec::new();
+                    let mut byte = [0; 1];
+                    while !head.ends_with(b"\r\n\r\n") {
+                        if stream.read(&mut byte).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        head.push(byte[0]);
+                    }
+                    let body = format!("{}:{}", conn, req);
+                    let connection = if close && req + 1 == requests {
+                        "Connection: close\r\n"
+                    } else {
+                        ""
+                    };
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n{}\r\n{}",
+                        body.len(),
+                        connection,
+                        body
+                    );
+                    stream.write_all(response.as_bytes()).unwrap();
+                }
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+/// Answers every request made to the returned address with
+/// `response`, keeping connections open. The number of the connection
+/// each request was received on, counting from zero, is sent to the
+/// returned receiver.
+#[cfg(feature = "compression")]
+pub fn serve_raw_keep_alive(response: &'static [u8]) -> (String, Receiver<usize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for (conn, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.unwrap();
+            let sender = sender.clone();
+            thread::spawn(move || loop {
+                let mut head = Vec::new();
+                let mut byte = [0; 1];
+                while !head.ends_with(b"\r\n\r\n") {
+                    if stream.read(&mut byte).unwrap_or(0) == 0 {
+                        return;
+                    }
+                    head.push(byte[0]);
+                }
+                sender.send(conn).ok();
+                if stream.write_all(response).is_err() {
+                    return;
+                }
+            });
+        }
+    });
+    (format!("http://{}", addr), receiver)
+}
+
+/// Answers the first request on each connection right away, and the
+/// following ones after `delay`, keeping connections open. The request
+/// line of every request received is sent to the returned receiver.
+pub fn serve_slow_keep_alive(delay: Duration) -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let sender = sender.clone();
+            thread::spawn(move || {
+                for req in 0.. {
+                    let mut head = Vec::new();
+                    let mut byte = [0; 1];
+                    while !head.ends_with(b"\r\n\r\n") {
+                        if stream.read(&mut byte).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        head.push(byte[0]);
+                    }
+                    let head = String::from_utf8_lossy(&head).into_owned();
+                    sender.send(head.lines().next().unwrap().to_string()).ok();
+                    if req > 0 {
+                        thread::sleep(delay);
+                    }
+                    let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                    if stream.write_all(response).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    (format!("http://{}", addr), receiver)
+}
+
+/// The certificate of the CA that signed the certificates of the TLS
+/// server and of its clients.
+#[cfg(feature = "https")]
+pub const CA_CERT: &[u8] = include_bytes!("certs/ca.pem");
+/// The certificate of the TLS server.
+#[cfg(feature = "https")]
+const SERVER_CERT: &[u8] = include_bytes!("certs/server.pem");
+/// The SHA-256 fingerprint of the certificate of the TLS server.
+#[cfg(feature = "https")]
+pub const SERVER_FINGERPRINT: &str = "82:22:90:7A:95:68:41:23:23:70:3E:AB:E7:36:D6:1A:\
+                                      48:B8:01:D3:53:79:3F:1F:A9:49:02:DB:17:A3:C2:12";
+/// The public key pin of the certificate of the TLS server.
+#[cfg(feature = "https")]
+pub const SERVER_PIN: &str = "grrv8Qp/3kJOgnyjHTtaRNe3FUMX9MWVUvT4x5f/XKE=";
+/// A client certificate signed by the CA, and its key.
+#[cfg(feature = "https")]
+pub const CLIENT_CERT: &[u8] = include_bytes!("certs/client.pem");
+#[cfg(feature = "https")]
+pub const CLIENT_KEY: &[u8] = include_bytes!("certs/client.key");
+
+/// Returns the DER-encoded certificate of the TLS server.
+#[cfg(feature = "https")]
+pub fn server_certificate() -> Vec<u8> {
+    let mut certs = pemfile::certs(&mut &SERVER_CERT[..]).unwrap();
+    certs.remove(0).0
+}
+
+/// Serves HTTPS for `localhost` on the returned URL, answering every
+/// request with a `418` whose body is `<connection> <client>
+/// <handshake>`, where connections are counted from zero, client is
+/// `client` if the client sent a certificate and `anonymous`
+/// otherwise, and handshake is `resumed` if the client resumed a
+/// previous TLS session and `full` otherwise. The `SNI` header of the
+/// response is the server name the client sent, or `-` if it sent
+/// none. If `client_auth` is set, clients have to send a certificate
+/// signed by the CA.
+#[cfg(feature = "https")]
+pub fn serve_tls(client_auth: bool) -> String {
+    serve_tls_versions(
+        client_auth,
+        &[ProtocolVersion::TLSv1_3, ProtocolVersion::TLSv1_2],
+        &[SERVER_CERT],
+    )
+}
+
+/// Like [`serve_tls`](fn.serve_tls.html), but only speaks TLS 1.2.
+#[cfg(feature = "https")]
+pub fn serve_tls12() -> String {
+    serve_tls_versions(false, &[ProtocolVersion::TLSv1_2], &[SERVER_CERT])
+}
+
+/// Like [`serve_tls`](fn.serve_tls.html), but sends `extra` after its
+/// own certificate, as if it were an intermediate.
+#[cfg(feature = "https")]
+pub fn serve_tls_chain(extra: &'static [u8]) -> String {
+    serve_tls_versions(
+        false,
+        &[ProtocolVersion::TLSv1_3, ProtocolVersion::TLSv1_2],
+        &[SERVER_CERT, extra],
+    )
+}
+
+#[cfg(feature = "https")]
+fn serve_tls_versions(client_auth: bool, versions: &[ProtocolVersion], chain: &[&[u8]]) -> String {
+    let mut roots = RootCertStore::empty();
+    roots.add_pem_file(&mut &CA_CERT[..]).unwrap();
+    let mut config = if client_auth {
+        ServerConfig::new(AllowAnyAuthenticatedClient::new(roots))
+    } else {
+        ServerConfig::new(NoClientAuth::new())
+    };
+    let certs = chain
+        .iter()
+        .flat_map(|pem| pemfile::certs(&mut &pem[..]).unwrap())
+        .collect();
+    let mut keys =
+        pemfile::pkcs8_private_keys(&mut &include_bytes!("certs/server.key")[..]).unwrap();
+    config.set_single_cert(certs, keys.remove(0)).unwrap();
+    config.set_protocols(&[b"http/1.1".to_vec()]);
+    config.versions = versions.to_vec();
+    let sessions = Arc::new(CountingSessions::default());
+    config.set_persistence(sessions.clone());
+    let config = Arc::new(config);
+
+    // Every loopback address reaches the server, not just 127.0.0.1.
+    let listener = TcpListener::bind("0.0.0.0:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for (conn, stream) in listener.incoming().enumerate() {
+            let mut session = ServerSession::new(&config);
+            let mut tcp = stream.unwrap();
+            let resumed_before = sessions.resumed.load(Ordering::SeqCst);
+            if session.complete_io(&mut tcp).is_err() {
+                continue;
+            }
+            let handshake = if sessions.resumed.load(Ordering::SeqCst) > resumed_before {
+                "resumed"
+            } else {
+                "full"
+            };
+            let mut stream = StreamOwned::new(session, tcp);
+            thread::spawn(move || loop {
+                let mut head = Vec::new();
+                let mut byte = [0; 1];
+                while !head.ends_with(b"\r\n\r\n") {
+                    if stream.read(&mut byte).unwrap_or(0) == 0 {
+                        return;
+                    }
+                    head.push(byte[0]);
+                }
+                let client = match stream.sess.get_peer_certificates() {
+                    Some(_) => "client",
+                    None => "anonymous",
+                };
+                let sni = stream.sess.get_sni_hostname().unwrap_or("-").to_string();
+                let body = format!("{} {} {}", conn, client, handshake);
+                let response = format!(
+                    "HTTP/1.1 418 I'm a teapot\r\nSNI: {}\r\nContent-Length: {}\r\n\r\n{}",
+                    sni,
+                    body.len(),
+                    body
+                );
+                if stream.write_all(response.as_bytes()).is_err() {
+                    return;
+                }
+            });
+        }
+    });
+    format!("https://localhost:{}", port)
+}
+
+/// Stores the TLS sessions of a server, counting the ones resumed.
+#[cfg(feature = "https")]
+#[derive(Default)]
+struct CountingSessions {
+    sessions: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+    resumed: AtomicUsize,
+}
+
+#[cfg(feature = "https")]
+impl StoresServerSessions for CountingSessions {
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> bool {
+        self.sessions.lock().unwrap().insert(key, value);
+        true
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let value = self.sessions.lock().unwrap().get(key).cloned();
+        if value.is_some() {
+            self.resumed.fetch_add(1, Ordering::SeqCst);
+        }
+        value
+    }
+
+    fn take(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let value = self.sessions.lock().unwrap().remove(key);
+        if value.is_some() {
+            self.resumed.fetch_add(1, Ordering::SeqCst);
+        }
+        value
+    }
+}
+
+/// Checks a Digest `Authorization` header for the user `user` with
+/// the password `pass`, answering the challenges sent by `/digest`.
+fn check_digest(authorization: &str, uri: &str) -> bool {
+    let param = |name| digest_param(authorization, name).unwrap_or_default();
+    let hash = |data: String| match param("algorithm").as_str() {
+        "MD5" => format!("{:x}", md5::compute(data)),
+        "SHA-256" => ring::digest::digest(&ring::digest::SHA256, data.as_bytes())
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect(),
+        _ => String::new(),
+    };
+    let ha1 = hash("user:mrq:pass".to_string());
+    let ha2 = hash(format!("GET:{}", uri));
+    let response = hash(format!(
+        "{}:abc:{}:{}:auth:{}",
+        ha1,
+        param("nc"),
+        param("cnonce"),
+        ha2
+    ));
+    param("username") == "user"
+        && param("uri") == uri
+        && param("opaque") == "xyz"
+        && param("qop") == "auth"
+        && param("algorithm") == digest_algorithm(uri)
+        && param("response") == response
+}
+
+/// The algorithm `/digest` expects the client to pick: it offers
+/// SHA-256 along with MD5, unless the URL asks for MD5 only.
+fn digest_algorithm(uri: &str) -> &'static str {
+    if uri.contains("md5") {
+        "MD5"
+    } else {
+        "SHA-256"
+    }
+}
+
+fn digest_param(authorization: &str, name: &str) -> Option<String> {
+    authorization
+        .trim_start_matches("Digest ")
+        .split(", ")
+        .filter_map(|param| {
+            let mut param = param.splitn(2, '=');
+            Some((param.next()?, param.next()?))
+        })
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.trim_matches('"').to_string())
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+}
+
 pub fn url(req: &str) -> String {
     format!("http://0.0.0.0:35562{}", req)
 }
 
-pub fn get_body(res: Result<mrq::Response, Error>) -> String {
+pub fn get_body(res: Result<mrq::Response, mrq::Error>) -> String {
     match res {
         Ok(mut response) => {
             let mut s = String::new();
@@ -103,7 +653,7 @@ pub fn get_body(res: Result<mrq::Response, Error>) -> String {
     }
 }
 
-pub fn get_status_code(resp: Result<mrq::Response, Error>) -> i32 {
+pub fn get_status_code(resp: Result<mrq::Response, mrq::Error>) -> i32 {
     match resp {
         Ok(response) => i32::from(&response.status),
         Err(err) => {